@@ -1,5 +1,10 @@
 use regex::Regex;
-use chrono::{Local, TimeZone};
+use crate::search_file::settings::{HashAlgorithm, ResultKind, SearchOptions, SymlinkMode, TimeField};
+use crate::search_file::glob::{split_patterns, validate_globs};
+use crate::search_file::file_types::parse_extensions;
+use crate::search_file::predicate::Predicate;
+use crate::search_file::rules::{RegexRule, RuleMode};
+use crate::search_file::empty::default_junk_names;
 
 /// 日期限制类型
 #[derive(Debug, Clone, PartialEq)]
@@ -21,11 +26,146 @@ pub enum DateLimitType {
     Months(i32, bool), // 新增：月份限制，值, whether_new(true=内/newer, false=外/older)
 }
 
-/// 正则表达式匹配目标
+/// 文件类型分类，选中后其中的扩展名加入白名单
 #[derive(Debug, Clone, PartialEq)]
-pub enum RegexTarget {
-    FileName,
-    FilePath,
+pub struct FileCategory {
+    pub name: String,
+    pub extensions: Vec<String>,
+    pub selected: bool,
+}
+
+impl FileCategory {
+    fn new(name: &str, extensions: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            extensions: parse_extensions(extensions),
+            selected: false,
+        }
+    }
+}
+
+/// 内置的文件类型分类，用户可以在过滤设置中修改其中的扩展名
+pub fn default_categories() -> Vec<FileCategory> {
+    vec![
+        FileCategory::new("图片", "jpg jpeg png gif bmp webp tif tiff svg ico heic raw psd"),
+        FileCategory::new("视频", "mp4 mkv avi mov wmv flv webm m4v mpg mpeg ts 3gp"),
+        FileCategory::new("音频", "mp3 wav flac aac ogg m4a wma opus ape"),
+        FileCategory::new("文档", "txt md pdf doc docx xls xlsx ppt pptx odt ods odp rtf csv epub"),
+        FileCategory::new("压缩包", "zip rar 7z tar gz tgz bz2 xz zst tar.gz tar.bz2 tar.xz iso"),
+        FileCategory::new("源代码", "rs c h cpp hpp cc py js ts jsx tsx java kt go cs rb php swift lua sh bat ps1 html css scss json toml yaml yml xml sql slint"),
+        FileCategory::new("可执行文件", "exe msi dll so dylib app apk deb rpm appimage bin com"),
+    ]
+}
+
+// 大小单位: KB/MB/GB/TB按1000进制，KiB/MiB/GiB/TiB按1024进制，单独的K/M/G/T与KiB/MiB/GiB/TiB相同
+const SIZE_UNITS: [(&str, u64); 14] = [
+    ("b", 1),
+    ("kb", 1_000),
+    ("mb", 1_000_000),
+    ("gb", 1_000_000_000),
+    ("tb", 1_000_000_000_000),
+    ("kib", 1 << 10),
+    ("mib", 1 << 20),
+    ("gib", 1 << 30),
+    ("tib", 1 << 40),
+    ("k", 1 << 10),
+    ("m", 1 << 20),
+    ("g", 1 << 30),
+    ("t", 1 << 40),
+    ("", 1),
+];
+
+// 格式化时使用的单位，从大到小
+const DISPLAY_UNITS: [(&str, u64); 8] = [
+    ("TiB", 1 << 40),
+    ("TB", 1_000_000_000_000),
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("KiB", 1 << 10),
+    ("KB", 1_000),
+];
+
+/// 解析带单位的大小，例如 4KB、1.5 GiB、100(字节)
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let unit = unit.trim();
+    let Some((_, multiplier)) = SIZE_UNITS.iter().find(|(name, _)| name.eq_ignore_ascii_case(unit)) else {
+        return Err(format!("未知的大小单位 {}，可用的单位: B、KB、KiB、MB、MiB、GB、GiB、TB、TiB", unit));
+    };
+    let bytes = if number.contains('.') {
+        let number: f64 = number.parse().map_err(|_| format!("无效的大小 {}", text))?;
+        let bytes = (number * *multiplier as f64).round();
+        if bytes >= u64::MAX as f64 {
+            return Err(format!("大小 {} 过大", text));
+        }
+        bytes as u64
+    } else {
+        let number: u64 = number.parse().map_err(|_| format!("无效的大小 {}", text))?;
+        number.checked_mul(*multiplier).ok_or_else(|| format!("大小 {} 过大", text))?
+    };
+    Ok(bytes)
+}
+
+/// 解析大小范围，返回包含两端的(下限, 上限)，没有上限时为u64::MAX
+/// 支持: 空(不限制)、>N、>=N、<N、<=N、A..B(可以省略一端)、N或=N(精确大小)
+pub fn parse_size_range(text: &str) -> Result<(u64, u64), String> {
+    let text = text.trim();
+    let (minimum, maximum) = if text.is_empty() {
+        (0, u64::MAX)
+    } else if let Some(rest) = text.strip_prefix(">=") {
+        (parse_size(rest)?, u64::MAX)
+    } else if let Some(rest) = text.strip_prefix('>') {
+        let size = parse_size(rest)?;
+        (size.checked_add(1).ok_or("没有更大的文件")?, u64::MAX)
+    } else if let Some(rest) = text.strip_prefix("<=") {
+        (0, parse_size(rest)?)
+    } else if let Some(rest) = text.strip_prefix('<') {
+        (0, parse_size(rest)?.checked_sub(1).ok_or("没有小于0字节的文件")?)
+    } else if let Some((minimum, maximum)) = text.split_once("..") {
+        let minimum = if minimum.trim().is_empty() { 0 } else { parse_size(minimum)? };
+        let maximum = if maximum.trim().is_empty() { u64::MAX } else { parse_size(maximum)? };
+        (minimum, maximum)
+    } else {
+        let size = parse_size(text.strip_prefix('=').unwrap_or(text))?;
+        (size, size)
+    };
+    if minimum > maximum {
+        return Err(format!("大小下限大于上限: {}", text));
+    }
+    Ok((minimum, maximum))
+}
+
+/// 把字节数格式化为能整除的最大单位，例如 4KiB、100GB、123B
+pub fn format_size(bytes: u64) -> String {
+    for (unit, multiplier) in DISPLAY_UNITS {
+        if bytes >= multiplier && bytes.is_multiple_of(multiplier) {
+            return format!("{}{}", bytes / multiplier, unit);
+        }
+    }
+    format!("{}B", bytes)
+}
+
+// 能否用带单位的形式表示
+fn has_unit(bytes: u64) -> bool {
+    DISPLAY_UNITS.iter().any(|(_, multiplier)| bytes >= *multiplier && bytes.is_multiple_of(*multiplier))
+}
+
+/// 把大小范围格式化为parse_size_range能够解析的文字，不限制时为空
+/// ">1MB"解析为1MB+1字节，格式化时还原为">"的形式
+pub fn format_size_range(minimum: u64, maximum: u64) -> String {
+    match (minimum, maximum) {
+        (0, u64::MAX) => String::new(),
+        _ if minimum == maximum => format_size(minimum),
+        (_, u64::MAX) if !has_unit(minimum) && has_unit(minimum - 1) => format!(">{}", format_size(minimum - 1)),
+        (_, u64::MAX) => format!(">={}", format_size(minimum)),
+        (0, _) if !has_unit(maximum) && has_unit(maximum + 1) => format!("<{}", format_size(maximum + 1)),
+        (0, _) => format!("<={}", format_size(maximum)),
+        _ => format!("{}..{}", format_size(minimum), format_size(maximum)),
+    }
 }
 
 /// 搜索过滤条件
@@ -37,11 +177,15 @@ pub struct SearchFilter {
     pub search_hidden_folders: bool,
     /// 是否搜索只读文件
     pub search_readonly_files: bool,
-    /// 文件大小限制 (单位: 字节)
+    /// 是否搜索系统文件(Unix下为设备、套接字和命名管道)
+    pub search_system_files: bool,
+    /// 文件大小限制 (单位: 字节，包含两端)，没有上限时为u64::MAX
     pub min_file_size: u64,
     pub max_file_size: u64,
     /// 日期限制
     pub date_limit: DateLimitType,
+    /// 日期限制作用于哪一个时间(修改/创建/访问)
+    pub time_field: TimeField,
     
     /// 完整指定日期模式的起始日期
     pub start_year: Option<i32>,
@@ -52,14 +196,39 @@ pub struct SearchFilter {
     pub end_month: Option<i32>,
     pub end_day: Option<i32>,
     
-    /// 快速选择模式相关
-    pub time_newer: Option<bool>, // true=内, false=外
-    
-    /// 正则表达式模式
-    pub regex_pattern: Option<Regex>,
-    pub regex_target: RegexTarget,
+    /// 按顺序排列的正则表达式规则，匹配文件名、路径、所在文件夹名或扩展名
+    pub regex_rules: Vec<RegexRule>,
+    /// 正则规则按顺序匹配还是全部匹配
+    pub rule_mode: RuleMode,
+    /// 文件内容需要匹配的正则表达式
+    pub content_pattern: Option<Regex>,
+    /// 是否反向匹配内容，即只保留内容中没有匹配行的文件
+    pub content_invert: bool,
     /// 是否记录哈希值
     pub record_hash: bool,
+    /// 记录哈希值和查找重复文件时使用的算法
+    pub hash_algorithm: HashAlgorithm,
+    /// 符号链接的处理方式
+    pub symlink_mode: SymlinkMode,
+    /// 结果中包含文件、目录还是两者
+    pub result_kind: ResultKind,
+    /// 只查找空文件和空目录
+    pub find_empty: bool,
+    /// 查找空目录时把junk_names中的系统文件视为不存在
+    pub junk_as_empty: bool,
+    pub junk_names: Vec<String>,
+    /// 包含/排除的通配符，匹配相对于搜索根目录的路径
+    pub include_globs: Vec<String>,
+    pub exclude_globs: Vec<String>,
+    /// 不进入的目录(目录名或路径)
+    pub excluded_directories: Vec<String>,
+    /// 是否遵循.gitignore/.ignore/.esignore
+    pub respect_ignore_files: bool,
+    /// 扩展名白名单/黑名单(小写，不带".")
+    pub include_extensions: Vec<String>,
+    pub exclude_extensions: Vec<String>,
+    /// 文件类型分类
+    pub categories: Vec<FileCategory>,
 }
 
 impl Default for SearchFilter {
@@ -68,48 +237,87 @@ impl Default for SearchFilter {
             search_hidden_files: true,
             search_hidden_folders: true,
             search_readonly_files: true,
+            search_system_files: true,
             min_file_size: 0,
-            max_file_size: 64 * 1024 * 1024 * 1024, // 64MB in bytes
+            max_file_size: u64::MAX, // 不限制
             date_limit: DateLimitType::None,
+            time_field: TimeField::Modified,
             start_year: None,
             start_month: None,
             start_day: None,
             end_year: None,
             end_month: None,
             end_day: None,
-            time_newer: Some(true),
-            regex_pattern: None,
-            regex_target: RegexTarget::FileName,
+            regex_rules: Vec::new(),
+            rule_mode: RuleMode::default(),
+            content_pattern: None,
+            content_invert: false,
             record_hash: false,
+            hash_algorithm: HashAlgorithm::default(),
+            symlink_mode: SymlinkMode::Follow,
+            result_kind: ResultKind::Files,
+            find_empty: false,
+            junk_as_empty: false,
+            junk_names: default_junk_names(),
+            include_globs: Vec::new(),
+            exclude_globs: Vec::new(),
+            excluded_directories: Vec::new(),
+            respect_ignore_files: false,
+            include_extensions: Vec::new(),
+            exclude_extensions: Vec::new(),
+            categories: default_categories(),
         }
     }
 }
 
+// 距今若干天/周/月/年的时间限制，负数没有意义(也不能转换为u64)，此时不限制时间
+fn add_relative_timelimit(options: &mut SearchOptions, value: i32, unit: &str, newer: bool) {
+    match u64::try_from(value) {
+        Ok(value) => options.add_type1_timelimit(value, unit, newer),
+        Err(_) => options.cancel_file_timelimit(),
+    }
+}
+
 impl SearchFilter {
     /// 从UI数据创建搜索过滤器
+    #[allow(clippy::too_many_arguments)] // 参数与UI中FilterData的各项一一对应
     pub fn from_ui_data(
         search_hidden_files: bool,
         search_hidden_folders: bool,
         search_readonly_files: bool,
-        min_file_size_mb: i32,
-        max_file_size_mb: i32,
+        search_system_files: bool,
+        file_size: &str, // 大小范围，见parse_size_range
         date_limit_type: i32,
         date_limit_value: i32,
         specific_year: i32,
         specific_month: i32,
         specific_day: i32,
-        regex_pattern: &str,
-        regex_target: i32,
+        regex_rules: Vec<RegexRule>, // 正则规则，模式为空的规则不生效
+        rules_match_all: bool, // 正则规则全部匹配(true)还是按顺序匹配(false)
+        content_pattern: &str, // 文件内容的正则表达式
         record_hash: bool,
         time_newer: bool, // 新增：是否"内"(true)或"外"(false)
         end_year: i32, // 新增：完整日期的结束年
         end_month: i32, // 新增：完整日期的结束月
         end_day: i32, // 新增：完整日期的结束日
+        symlink_mode: i32, // 0=忽略, 1=列出链接本身, 2=跟随
+        include_globs: &str, // 以";"分隔的包含通配符
+        exclude_globs: &str, // 以";"分隔的排除通配符
+        excluded_directories: &str, // 以";"分隔的排除目录
+        respect_ignore_files: bool,
+        include_extensions: &str, // 扩展名白名单，以";"、","或空格分隔
+        exclude_extensions: &str, // 扩展名黑名单
+        categories: Vec<FileCategory>, // 文件类型分类及其选中状态
+        time_field: i32, // 0=修改时间, 1=创建时间, 2=访问时间
+        content_invert: bool, // 只保留内容不匹配的文件
+        result_kind: i32, // 0=文件, 1=目录, 2=两者
+        find_empty: bool,
+        junk_as_empty: bool,
+        junk_names: &str, // 以";"分隔的文件名
+        hash_algorithm: i32, // HashAlgorithm::ALL中的下标
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        println!("from_ui_data: date_limit_type={}, time_newer={}", date_limit_type, time_newer);
-        // 转换文件大小从MB到字节
-        let min_file_size = (min_file_size_mb as u64) * 1024 * 1024;
-        let max_file_size = (max_file_size_mb as u64) * 1024 * 1024;
+        // 解析文件大小范围
+        let (min_file_size, max_file_size) = parse_size_range(file_size)?;
           // 转换日期限制
         let date_limit = match date_limit_type {
             0 => DateLimitType::None,
@@ -128,39 +336,267 @@ impl SearchFilter {
             _ => DateLimitType::None,
         };
         
-        // 转换正则表达式
-        let regex_pattern = if regex_pattern.trim().is_empty() {
+        // 检查正则规则并转换内容正则表达式
+        for (index, rule) in regex_rules.iter().enumerate() {
+            if let Err(e) = rule.validate() {
+                return Err(format!("第{}条正则规则 \"{}\" 无效: {}", index + 1, rule.pattern, e).into());
+            }
+        }
+        let content_pattern = if content_pattern.trim().is_empty() {
             None
         } else {
-            Some(Regex::new(regex_pattern)?)
+            Some(Regex::new(content_pattern)?)
         };
         
-        // 转换正则表达式目标
-        let regex_target = match regex_target {
-            0 => RegexTarget::FileName,
-            _ => RegexTarget::FilePath,
-        };
+        // 转换通配符
+        let include_globs = split_patterns(include_globs);
+        let exclude_globs = split_patterns(exclude_globs);
+        validate_globs(&include_globs)?;
+        validate_globs(&exclude_globs)?;
+
           Ok(Self {
             search_hidden_files,
             search_hidden_folders,
             search_readonly_files,
+            search_system_files,
             min_file_size,
             max_file_size,
             date_limit,
+            time_field: match time_field {
+                1 => TimeField::Created,
+                2 => TimeField::Accessed,
+                _ => TimeField::Modified,
+            },
             start_year: None, // 在调用site提供额外参数
             start_month: None,
             start_day: None,
             end_year: None,
             end_month: None,
             end_day: None,
-            time_newer: Some(true), // 默认为"内"
-            regex_pattern,
-            regex_target,
+            regex_rules,
+            rule_mode: if rules_match_all { RuleMode::All } else { RuleMode::Ordered },
+            content_pattern,
+            content_invert,
             record_hash,
+            hash_algorithm: usize::try_from(hash_algorithm).ok()
+                .and_then(|index| HashAlgorithm::ALL.get(index).copied())
+                .unwrap_or_default(),
+            symlink_mode: match symlink_mode {
+                0 => SymlinkMode::Skip,
+                1 => SymlinkMode::List,
+                _ => SymlinkMode::Follow,
+            },
+            result_kind: match result_kind {
+                1 => ResultKind::Directories,
+                2 => ResultKind::Both,
+                _ => ResultKind::Files,
+            },
+            find_empty,
+            junk_as_empty,
+            junk_names: split_patterns(junk_names),
+            include_globs,
+            exclude_globs,
+            excluded_directories: split_patterns(excluded_directories),
+            respect_ignore_files,
+            include_extensions: parse_extensions(include_extensions),
+            exclude_extensions: parse_extensions(exclude_extensions),
+            categories,
         })
     }
     
+    /// 根据过滤器设置构建一次搜索所用的SearchOptions
+    pub fn to_search_options(&self) -> SearchOptions {
+        let mut options = SearchOptions {
+            // 隐藏文件和目录设置
+            search_hidden_files: self.search_hidden_files,
+            search_hidden_folders: self.search_hidden_folders,
+            
+            // 只读文件设置
+            search_readonly: self.search_readonly_files,
+            
+            // 系统文件设置
+            search_system_files: self.search_system_files,
+            
+            // 文件大小限制
+            search_filesize_minimum_limit: self.min_file_size,
+            search_filesize_maximum_limit: self.max_file_size,
+            
+            // 记录哈希值设置
+            save_hash: self.record_hash,
+            hash_algorithm: self.hash_algorithm,
+            
+            // 符号链接处理方式
+            symlink_mode: self.symlink_mode,
+            
+            // 结果中包含文件、目录还是两者
+            result_kind: self.result_kind,
+            
+            // 空文件和空目录
+            find_empty: self.find_empty,
+            junk_as_empty: self.junk_as_empty,
+            junk_names: self.junk_names.clone(),
+            
+            // 正则表达式设置: 文件名/路径使用正则规则，传入搜索的正则表达式只用于匹配文件内容
+            regex_contain_path: false,
+            search_content: self.content_pattern.is_some(),
+            regex_invert: self.content_invert,
+            regex_rules: self.regex_rules.clone(),
+            rule_mode: self.rule_mode,
+            
+            // 通配符设置
+            include_globs: self.include_globs.clone(),
+            exclude_globs: self.exclude_globs.clone(),
+            
+            // 排除目录和忽略文件设置
+            excluded_directories: self.excluded_directories.clone(),
+            respect_ignore_files: self.respect_ignore_files,
+            
+            // 日期限制比较的时间
+            search_time_field: self.time_field,
+            
+            // 扩展名和文件类型设置
+            include_extensions: self.allowed_extensions(),
+            exclude_extensions: self.exclude_extensions.clone(),
+            
+            ..SearchOptions::default()
+        };
+
+        // 时间限制设置
+        match &self.date_limit {
+            DateLimitType::None => {
+                // 取消时间限制
+                options.cancel_file_timelimit();
+            },
+            DateLimitType::Days(days, whether_new) => {
+                // 使用天数限制
+                add_relative_timelimit(&mut options, *days, "day", *whether_new);
+            },
+            DateLimitType::Weeks(weeks, whether_new) => {
+                // 使用周数限制
+                add_relative_timelimit(&mut options, *weeks, "week", *whether_new);
+            },
+            DateLimitType::Months(months, whether_new) => {
+                // 使用月数限制
+                add_relative_timelimit(&mut options, *months, "month", *whether_new);
+            },
+            DateLimitType::Years(years, whether_new) => {
+                // 使用年数限制
+                add_relative_timelimit(&mut options, *years, "year", *whether_new);
+            },            
+            DateLimitType::Specific { 
+                minimum_year, minimum_month, minimum_day,
+                maximum_year, maximum_month, maximum_day 
+            } => {
+                // 直接使用Specific中的起始和结束日期值
+                options.add_type2_timelimit(
+                    *minimum_year, 
+                    *minimum_month, 
+                    *minimum_day,
+                    *maximum_year, 
+                    *maximum_month, 
+                    *maximum_day
+                );
+            },
+        }
+
+        options
+    }
+    
+    /// 转换为过滤条件树，搜索时对每个文件求值
+    /// 正则表达式无效时返回错误
+    pub fn to_predicate(&self) -> Result<Predicate, regex::Error> {
+        let options = self.to_search_options();
+        let regex = self.content_pattern.as_ref().map(|regex| regex.as_str()).unwrap_or("");
+        Predicate::from_options(&options, regex)
+    }
+    
+    /// 实际生效的扩展名白名单: 手动输入的扩展名加上选中分类中的扩展名
+    pub fn allowed_extensions(&self) -> Vec<String> {
+        let mut extensions = self.include_extensions.clone();
+        for category in self.categories.iter().filter(|category| category.selected) {
+            extensions.extend(category.extensions.iter().cloned());
+        }
+        extensions
+    }
+    
     
     
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_units() {
+        assert_eq!(parse_size("4KB"), Ok(4_000));
+        assert_eq!(parse_size("4KiB"), Ok(4_096));
+        assert_eq!(parse_size("4k"), Ok(4_096));
+        assert_eq!(parse_size("1.5 GiB"), Ok(3 << 29));
+        assert_eq!(parse_size("100"), Ok(100));
+        assert_eq!(parse_size("2mb"), Ok(2_000_000));
+        assert!(parse_size("4XB").is_err());
+        assert!(parse_size("KB").is_err());
+    }
+
+    #[test]
+    fn size_overflow() {
+        // 99999TB约为1e17字节，仍然在u64范围内
+        assert_eq!(parse_size("99999TB"), Ok(99_999_000_000_000_000));
+        assert!(parse_size("99999999TB").is_err());
+        assert!(parse_size("99999999.5TB").is_err());
+        assert!(parse_size_range(">99999999TB").is_err());
+        assert!(parse_size_range(&format!(">{}", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn size_ranges() {
+        assert_eq!(parse_size_range(""), Ok((0, u64::MAX)));
+        assert_eq!(parse_size_range(">1mb"), Ok((1_000_001, u64::MAX)));
+        assert_eq!(parse_size_range(">=1mb"), Ok((1_000_000, u64::MAX)));
+        assert_eq!(parse_size_range("<4KiB"), Ok((0, 4_095)));
+        assert_eq!(parse_size_range("<=4KiB"), Ok((0, 4_096)));
+        assert_eq!(parse_size_range("..4KB"), Ok((0, 4_000)));
+        assert_eq!(parse_size_range("1MB.."), Ok((1_000_000, u64::MAX)));
+        assert_eq!(parse_size_range("1MB..5MB"), Ok((1_000_000, 5_000_000)));
+        assert_eq!(parse_size_range("=0"), Ok((0, 0)));
+        assert_eq!(parse_size_range("0"), Ok((0, 0)));
+        assert!(parse_size_range("<0").is_err());
+        assert!(parse_size_range("5MB..1MB").is_err());
+    }
+
+    #[test]
+    fn size_range_format_round_trips() {
+        for text in ["", ">1MB", ">=1023B", "<4KiB", "<=4097B", "1MB..5MiB", "0B", "99999TB", ">1TiB"] {
+            let (minimum, maximum) = parse_size_range(text).unwrap();
+            assert_eq!(format_size_range(minimum, maximum), text);
+        }
+        for (minimum, maximum) in [(0, u64::MAX), (0, 0), (1, u64::MAX), (0, u64::MAX - 1), (123, 456), (1_000_001, u64::MAX)] {
+            assert_eq!(parse_size_range(&format_size_range(minimum, maximum)), Ok((minimum, maximum)));
+        }
+    }
+
+    #[test]
+    fn negative_relative_date_is_not_limited() {
+        let mut filter = SearchFilter {
+            date_limit: DateLimitType::Days(-3, true),
+            ..SearchFilter::default()
+        };
+        assert!(!filter.to_search_options().search_timelimit);
+        filter.date_limit = DateLimitType::Years(i32::MIN, false);
+        assert!(!filter.to_search_options().search_timelimit);
+
+        filter.date_limit = DateLimitType::Days(0, true);
+        let options = filter.to_search_options();
+        assert!(options.search_timelimit);
+        assert_eq!(options.search_compare_with_current_time_limit, 0);
+    }
+
+    #[test]
+    fn system_files_setting_reaches_search_options() {
+        assert!(SearchFilter::default().to_search_options().search_system_files);
+        let filter = SearchFilter { search_system_files: false, ..SearchFilter::default() };
+        assert!(!filter.to_search_options().search_system_files);
+    }
+}
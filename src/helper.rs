@@ -1,119 +1,101 @@
 use crate::filter::SearchFilter;
-use crate::search_file::settings;
-use std::path::PathBuf;
-use chrono::Datelike;
+use std::path::{Path, PathBuf};
 
-/// 帮助将UI设置应用到search_file模块的settings
+/// 帮助将UI设置转换为search_file模块的搜索参数
 pub struct SearchHelper;
 
 impl SearchHelper {
-    /// 应用过滤器设置到search_file模块
-    pub fn apply_filter_settings(filter: &SearchFilter) {
-        // 使用settings.rs中定义的函数
-        
-        // 隐藏文件和目录设置
-        settings::modify_search_hidden_files(filter.search_hidden_files);
-        settings::modify_search_hidden_folders(filter.search_hidden_folders);
-        
-        // 只读文件设置
-        settings::modify_search_readonly(filter.search_readonly_files);
-        
-        // 系统文件设置
-        settings::modify_search_system_files(true); // 默认搜索系统文件
-        
-        // 文件大小限制
-        settings::modify_search_filesize_minimum_limit(filter.min_file_size);
-        settings::modify_search_filesize_maximum_limit(filter.max_file_size);
-        
-        // 记录哈希值设置
-        settings::modify_save_hash(filter.record_hash);
-        
-        // 正则表达式设置
-        settings::modify_regex_contain_path(match filter.regex_target {
-            crate::filter::RegexTarget::FileName => false,
-            crate::filter::RegexTarget::FilePath => true,
-        });        // 时间限制设置
-        match &filter.date_limit {
-            crate::filter::DateLimitType::None => {
-                // 取消时间限制
-                settings::cancel_file_timelimit();
-            },
-            crate::filter::DateLimitType::Days(days, whether_new) => {
-                // 使用天数限制
-                let unit = "day";
-                settings::add_type1_timelimit(*days as u64, unit, *whether_new);
-            },
-            crate::filter::DateLimitType::Weeks(weeks, whether_new) => {
-                // 使用周数限制
-                let unit = "week";
-                settings::add_type1_timelimit(*weeks as u64, unit, *whether_new);
-            },
-            crate::filter::DateLimitType::Months(months, whether_new) => {
-                // 使用月数限制
-                let unit = "month";
-                settings::add_type1_timelimit(*months as u64, unit, *whether_new);
-            },
-            crate::filter::DateLimitType::Years(years, whether_new) => {
-                // 使用年数限制
-                let unit = "year";
-                settings::add_type1_timelimit(*years as u64, unit, *whether_new);
-            },            
-            crate::filter::DateLimitType::Specific { 
-                minimum_year, minimum_month, minimum_day,
-                maximum_year, maximum_month, maximum_day 
-            } => {
-                // 直接使用Specific中的起始和结束日期值
-                println!("使用完整日期范围筛选：从 {}/{}/{} 到 {}/{}/{}",
-                    minimum_year, minimum_month, minimum_day,
-                    maximum_year, maximum_month, maximum_day);
-                    
-                settings::add_type2_timelimit(
-                    *minimum_year, 
-                    *minimum_month, 
-                    *minimum_day,
-                    *maximum_year, 
-                    *maximum_month, 
-                    *maximum_day
-                );
-            },
-        }
-    }
     
     /// 执行搜索
     /// 
     /// # Arguments
     /// 
-    /// * `paths` - 要搜索的路径列表
-    /// * `search_depth` - 搜索深度
-    /// * `regex_pattern` - 正则表达式模式
-    /// * `filter` - 过滤器设置
+    /// * `roots` - 要搜索的根目录列表，每个根目录有各自的深度范围
+    /// * `filter` - 过滤器设置，其中的正则表达式(content_pattern)和其他条件一起构成过滤条件树
+    /// * `cancel` - 取消标记，取消后返回已经找到的部分结果
     /// 
     /// # Returns
     /// 
-    /// 搜索结果列表
+    /// 搜索结果列表，以及记录了无法读取路径的搜索报告；正则表达式无效时返回错误
+    #[allow(dead_code)] // 界面使用perform_search_streaming
     pub fn perform_search(
-        paths: &[PathBuf], 
-        search_depth: u8, 
-        regex_pattern: &str,
-        filter: &SearchFilter
-    ) -> Vec<crate::search_file::SingleFileInformations> {
-        // 先应用过滤器设置
-        Self::apply_filter_settings(filter);
+        roots: &[crate::search_file::SearchRoot], 
+        filter: &SearchFilter,
+        cancel: &crate::search_file::CancelToken
+    ) -> Result<(Vec<crate::search_file::SingleFileInformations>, crate::search_file::SearchReport), regex::Error> {
+        // 根据过滤器设置构建本次搜索的参数和过滤条件树
+        let options = filter.to_search_options();
+        let predicate = filter.to_predicate()?;
         
         // 执行搜索
-        crate::search_file::get_files(paths, search_depth, &regex_pattern.to_string())
+        Ok(crate::search_file::get_files(roots, &predicate, options, cancel))
     }
     
-    /// 去重搜索结果
-    pub fn remove_duplicates(
-        files: &mut Vec<crate::search_file::SingleFileInformations>
-    ) {
-        crate::search_file::unique_files(files);
+    /// 执行搜索，并在搜索过程中不断返回结果
+    /// 
+    /// 每搜索完一个目录，其中找到的文件会通过`on_batch`交给调用者(在工作线程中调用)，
+    /// `progress`中记录已扫描的目录数和已匹配的文件数，`cancel`用于中途停止搜索
+    /// 
+    /// # Returns
+    /// 
+    /// 记录了无法读取路径的搜索报告；正则表达式无效时返回错误，不进行搜索
+    pub fn perform_search_streaming(
+        roots: &[crate::search_file::SearchRoot], 
+        filter: &SearchFilter,
+        progress: &crate::search_file::SearchProgress,
+        cancel: &crate::search_file::CancelToken,
+        on_batch: &(dyn Fn(Vec<crate::search_file::SingleFileInformations>) + Sync)
+    ) -> Result<crate::search_file::SearchReport, regex::Error> {
+        // 根据过滤器设置构建本次搜索的参数和过滤条件树
+        let options = filter.to_search_options();
+        let predicate = filter.to_predicate()?;
+        
+        // 执行搜索
+        Ok(crate::search_file::get_files_streaming(roots, &predicate, options, progress, cancel, on_batch))
+    }
+    
+    /// 查找内容相同的文件
+    /// 
+    /// 先按大小分组，再比较文件首尾的部分哈希，只有仍然相同的文件才计算完整哈希，
+    /// 重复文件用`algorithm`计算的完整哈希会写入其`hash`字段
+    /// 
+    /// # Returns
+    /// 
+    /// 每组重复文件在`files`中的下标，以及记录了无法读取文件的报告
+    pub fn find_duplicates(
+        files: &mut [crate::search_file::SingleFileInformations],
+        algorithm: crate::search_file::settings::HashAlgorithm,
+        progress: &crate::search_file::duplicates::DuplicateProgress,
+        cancel: &crate::search_file::CancelToken
+    ) -> (Vec<Vec<usize>>, crate::search_file::SearchReport) {
+        let mut report = crate::search_file::SearchReport::default();
+        let threads = crate::search_file::settings::default_worker_threads();
+        let groups = crate::search_file::duplicates::find_duplicates(files, algorithm, threads, progress, cancel, &mut report);
+        (groups, report)
+    }
+    
+    /// 清理哈希值缓存中文件已经不存在或已经改变的条目
+    /// 
+    /// # Returns
+    /// 
+    /// 删除的条目数量和剩余的条目数量
+    pub fn prune_hash_cache() -> std::io::Result<(usize, usize)> {
+        let cache = crate::search_file::hash_cache::HashCache::open();
+        let removed = cache.prune();
+        cache.save()?;
+        Ok((removed, cache.len()))
+    }
+    
+    /// 清空哈希值缓存
+    pub fn clear_hash_cache() -> std::io::Result<()> {
+        let cache = crate::search_file::hash_cache::HashCache::open();
+        cache.clear();
+        cache.save()
     }
     
     /// 排序搜索结果
     pub fn sort_results(
-        files: &mut Vec<crate::search_file::SingleFileInformations>,
+        files: &mut [crate::search_file::SingleFileInformations],
         sort_type: &str,
         reversed: bool
     ) {
@@ -137,10 +119,39 @@ impl SearchHelper {
         }
     }
     
+    /// 把搜索结果导出为sha256sum/md5sum格式的校验文件
+    /// 
+    /// 校验文件的扩展名(如`.md5`)或文件名(如`SHA1SUMS`)指明了算法时使用该算法，否则使用`algorithm`
+    /// 
+    /// # Returns
+    /// 
+    /// 写入的文件数量，以及记录了无法计算哈希的文件的报告
+    pub fn export_checksums(
+        files: &[crate::search_file::SingleFileInformations],
+        manifest: &Path,
+        algorithm: crate::search_file::settings::HashAlgorithm,
+        cancel: &crate::search_file::CancelToken
+    ) -> std::io::Result<crate::search_file::checksum::ManifestExport> {
+        let algorithm = crate::search_file::settings::HashAlgorithm::from_manifest_name(manifest).unwrap_or(algorithm);
+        crate::search_file::checksum::export_manifest(files, manifest, algorithm, cancel)
+    }
+    
+    /// 导入校验文件，逐个校验其中列出的文件
+    /// 
+    /// # Returns
+    /// 
+    /// 存在的文件(作为新的结果列表)，以及一致、不一致和缺失的文件
+    pub fn import_checksums(
+        manifest: &Path,
+        cancel: &crate::search_file::CancelToken
+    ) -> std::io::Result<crate::search_file::checksum::ManifestImport> {
+        crate::search_file::checksum::import_manifest(manifest, cancel)
+    }
+    
     /// 复制文件到目标目录
     pub fn copy_files_to(
         files: &[crate::search_file::SingleFileInformations],
-        destination: &PathBuf
+        destination: &Path
     ) -> std::io::Result<()> {
         crate::search_file::copy_files(files, destination)
     }
@@ -148,7 +159,7 @@ impl SearchHelper {
     /// 移动文件到目标目录
     pub fn move_files_to(
         files: &[crate::search_file::SingleFileInformations],
-        destination: &PathBuf
+        destination: &Path
     ) -> std::io::Result<()> {
         crate::search_file::move_files(files, destination)
     }
@@ -156,16 +167,24 @@ impl SearchHelper {
     /// 删除文件
     pub fn delete_files(
         files: &[crate::search_file::SingleFileInformations]
-    ) -> std::io::Result<()> {
+    ) -> crate::search_file::FileOperationReport {
         crate::search_file::delete_files(files)
     }
     
+    /// 把空文件和空目录移到回收站，从最深的条目开始
+    pub fn clean_empty(
+        files: &[crate::search_file::SingleFileInformations],
+        junk: &[String]
+    ) -> crate::search_file::empty::CleanupReport {
+        crate::search_file::empty::remove_empty(files, junk)
+    }
+    
     /// 映射文件（保持目录结构）
     pub fn map_files(
         files: &[crate::search_file::SingleFileInformations],
         source: &PathBuf,
-        destination: &PathBuf
-    ) {
+        destination: &Path
+    ) -> std::io::Result<crate::search_file::FileOperationReport> {
         crate::search_file::mapping_files(files, source, destination)
     }
 }
@@ -3,6 +3,7 @@
 
 mod ui_handler;
 mod filter;
+mod query;
 mod search_file;
 mod helper;
 
@@ -10,8 +11,6 @@ mod helper;
 use std::error::Error;
 use ui_handler::UIHandler;
 
-slint::include_modules!();
-
 fn main() -> Result<(), Box<dyn Error>> {
     // 创建UI处理器
     let ui_handler = UIHandler::new()?;
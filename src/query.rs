@@ -0,0 +1,752 @@
+// 类似Everything的单行查询语法，例如:
+//   ext:rs,toml size:>1mb dm:lastweek path:src !name:test
+// 查询由空白分隔的条件组成，所有条件同时生效；值中含有空白时用双引号括起来，值中的双引号写作\"，条件前加"!"表示取反
+// 每一种条件都对应过滤设置页面中的一项: parse_query把查询转换为SearchFilter，to_query把SearchFilter转换回查询，
+// 因此搜索页面的查询框和过滤设置页面始终保持一致
+//
+// 支持的条件:
+//   文字 / name:文字        文件名包含该文字        !name: 排除文件名包含该文字的文件
+//   regex:正则              文件名匹配正则表达式    !regex: 排除
+//   pathregex:正则          完整路径匹配正则表达式
+//   parent:文字             所在文件夹名包含该文字  parentregex: 所在文件夹名匹配正则
+//   extregex:正则           扩展名匹配正则表达式
+//   content:文字            文件内容包含该文字      contentregex: 文件内容匹配正则   !content: 内容不包含
+//   以上条件前加i表示忽略大小写，例如 iname:readme、!iparentregex:^tests?$
+//   名称、路径、文件夹名和扩展名条件组成正则规则，文件必须满足其中每一个条件
+//   rules:ordered           正则规则改为按顺序匹配，与.gitignore一样后面的条件优先，见search_file::rules
+//   path:文字               相对路径包含该文字      !path: 排除路径中包含该文字的文件和目录
+//   glob:通配符             包含通配符              !glob: 排除通配符
+//   ext:rs,toml             扩展名白名单            !ext: 扩展名黑名单
+//   type:图片,视频          选中文件类型分类
+//   size:>1MB size:<=4KiB size:1MB..5MB size:0     文件大小，KB/MB按1000进制，KiB/MiB按1024进制
+//   dm: dc: da:             修改/创建/访问时间，值为today、yesterday、lastweek、lastmonth、lastyear、
+//                           last3days、2024-01-31、2024-01-01..2024-06-30(可以省略一端)；!dm:lastweek 表示一周以外
+//   !dir:target             不进入的目录
+//   hidden: hiddendir: readonly: system: ignore: hash:     yes/no
+//   empty:yes               只查找空文件和空目录    junk:yes 把Thumbs.db等系统文件视为不存在
+//   hashalgo:xxh3|sha1|sha256|sha512|md5   记录哈希值和查找重复文件使用的算法
+//   links:skip|list|follow  符号链接的处理方式
+//   kind:files|dirs|both    结果中包含文件、目录还是两者，目录的大小是其中所有文件的总大小
+
+use std::fmt;
+use chrono::{Datelike, Local, NaiveDate};
+use regex::Regex;
+
+use crate::filter::{format_size_range, parse_size_range, DateLimitType, SearchFilter};
+use crate::search_file::file_types::parse_extensions;
+use crate::search_file::glob::validate_globs;
+use crate::search_file::rules::{RegexRule, RuleMode, RuleTarget};
+use crate::search_file::settings::{HashAlgorithm, ResultKind, SymlinkMode, TimeField};
+
+/// 查询中出错的条件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub position: usize, // 出错的条件在查询中的位置(从1开始的字符序号)
+    pub token: String, // 出错的条件原文
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第{}个字符处的条件 \"{}\" 有误: {}", self.position, self.token, self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+// 查询中的一个条件
+struct Token {
+    position: usize,
+    text: String, // 原文，用于错误提示
+    negated: bool,
+    key: Option<String>, // 冒号前的条件类型，没有时为普通文字
+    value: String, // 去掉引号后的值
+}
+
+impl Token {
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        QueryError {
+            position: self.position,
+            token: self.text.clone(),
+            message: message.into(),
+        }
+    }
+}
+
+// 按空白拆分查询，引号内的空白不拆分，\"表示值中的双引号；第一个不在引号内的冒号把条件类型和值分开
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        if chars[index].is_whitespace() {
+            index += 1;
+            continue;
+        }
+
+        let start = index;
+        let mut unquoted = String::new();
+        let mut colon: Option<usize> = None;
+        let mut in_quotes = false;
+        while index < chars.len() && (in_quotes || !chars[index].is_whitespace()) {
+            match chars[index] {
+                '\\' if chars.get(index + 1) == Some(&'"') => {
+                    unquoted.push('"');
+                    index += 1;
+                },
+                '"' => in_quotes = !in_quotes,
+                ':' if !in_quotes && colon.is_none() => {
+                    colon = Some(unquoted.len());
+                    unquoted.push(':');
+                },
+                c => unquoted.push(c),
+            }
+            index += 1;
+        }
+        let text: String = chars[start..index].iter().collect();
+        if in_quotes {
+            return Err(QueryError {
+                position: start + 1,
+                token: text,
+                message: "引号没有闭合".to_string(),
+            });
+        }
+
+        let negated = text.starts_with('!');
+        let body_start = if negated { 1 } else { 0 };
+        let (key, value) = match colon {
+            Some(colon) if colon > body_start && unquoted[body_start..colon].chars().all(|c| c.is_ascii_alphabetic()) => (
+                Some(unquoted[body_start..colon].to_lowercase()),
+                unquoted[colon + 1..].to_string(),
+            ),
+            _ => (None, unquoted[body_start..].to_string()),
+        };
+        tokens.push(Token {
+            position: start + 1,
+            text,
+            negated,
+            key,
+            value,
+        });
+    }
+    Ok(tokens)
+}
+
+// 各类条件中只能出现一次的部分
+#[derive(Default)]
+struct Seen {
+    content: bool,
+    size: bool,
+    date: bool,
+    rules: bool,
+    switches: Vec<String>,
+}
+
+// 把查询转换为过滤条件
+// 查询描述了全部过滤条件，没有出现的条件使用默认值；只有各文件类型分类包含的扩展名和视为空的系统文件名沿用base中的设置
+pub fn parse_query(query: &str, base: &SearchFilter) -> Result<SearchFilter, QueryError> {
+    let mut filter = SearchFilter {
+        categories: base.categories.iter().cloned().map(|mut category| {
+            category.selected = false;
+            category
+        }).collect(),
+        junk_names: base.junk_names.clone(),
+        rule_mode: RuleMode::All,
+        ..SearchFilter::default()
+    };
+    let mut seen = Seen::default();
+
+    for token in tokenize(query)? {
+        if token.value.is_empty() {
+            return Err(token.error("缺少条件的值"));
+        }
+        if let Some((target, literal, case_insensitive)) = text_key(token.key.as_deref()) {
+            let pattern = if literal { regex::escape(&token.value) } else { token.value.clone() };
+            match target {
+                Some(target) => add_rule(&mut filter, &token, pattern, target, case_insensitive)?,
+                None => set_content(&mut filter, &mut seen, &token, pattern, case_insensitive)?,
+            }
+            continue;
+        }
+        match token.key.as_deref() {
+            Some("path") => {
+                let text = escape_glob(&token.value);
+                let pattern = if token.negated {
+                    format!("**/*{}*", text)
+                } else {
+                    format!("{{**/*{0}*,**/*{0}*/**}}", text)
+                };
+                add_glob(&mut filter, &token, pattern)?;
+            },
+            Some("glob") => {
+                add_glob(&mut filter, &token, token.value.clone())?;
+            },
+            Some("ext") => {
+                let extensions = parse_extensions(&token.value.replace(',', " "));
+                if extensions.is_empty() {
+                    return Err(token.error("缺少扩展名"));
+                }
+                if token.negated {
+                    filter.exclude_extensions.extend(extensions);
+                } else {
+                    filter.include_extensions.extend(extensions);
+                }
+            },
+            Some("type") => {
+                not_negated(&token)?;
+                for name in token.value.split(',').map(|name| name.trim()).filter(|name| !name.is_empty()) {
+                    let Some(category) = filter.categories.iter_mut().find(|category| category.name.eq_ignore_ascii_case(name)) else {
+                        let names: Vec<&str> = base.categories.iter().map(|category| category.name.as_str()).collect();
+                        return Err(token.error(format!("没有名为 {} 的文件类型，可用的类型: {}", name, names.join(", "))));
+                    };
+                    category.selected = true;
+                }
+            },
+            Some("size") => {
+                not_negated(&token)?;
+                if std::mem::replace(&mut seen.size, true) {
+                    return Err(token.error("size:只能出现一次"));
+                }
+                let (minimum, maximum) = parse_size_range(&token.value).map_err(|message| token.error(message))?;
+                filter.min_file_size = minimum;
+                filter.max_file_size = maximum;
+            },
+            Some(key @ ("dm" | "dc" | "da")) => {
+                if std::mem::replace(&mut seen.date, true) {
+                    return Err(token.error("dm:/dc:/da:只能出现一个"));
+                }
+                filter.time_field = match key {
+                    "dc" => TimeField::Created,
+                    "da" => TimeField::Accessed,
+                    _ => TimeField::Modified,
+                };
+                filter.date_limit = parse_date_limit(&token.value, !token.negated).map_err(|message| token.error(message))?;
+            },
+            Some("dir") => {
+                if !token.negated {
+                    return Err(token.error("dir:只能用于排除目录，请写作 !dir:"));
+                }
+                filter.excluded_directories.push(token.value.clone());
+            },
+            Some(key @ ("hidden" | "hiddendir" | "readonly" | "system" | "ignore" | "hash" | "empty" | "junk")) => {
+                not_negated(&token)?;
+                if seen.switches.iter().any(|seen_key| seen_key == key) {
+                    return Err(token.error(format!("{}:只能出现一次", key)));
+                }
+                seen.switches.push(key.to_string());
+                let value = parse_switch(&token.value).ok_or_else(|| token.error("值应为 yes 或 no"))?;
+                match key {
+                    "hidden" => filter.search_hidden_files = value,
+                    "hiddendir" => filter.search_hidden_folders = value,
+                    "readonly" => filter.search_readonly_files = value,
+                    "system" => filter.search_system_files = value,
+                    "ignore" => filter.respect_ignore_files = value,
+                    "empty" => filter.find_empty = value,
+                    "junk" => filter.junk_as_empty = value,
+                    _ => filter.record_hash = value,
+                }
+            },
+            Some("rules") => {
+                not_negated(&token)?;
+                if std::mem::replace(&mut seen.rules, true) {
+                    return Err(token.error("rules:只能出现一次"));
+                }
+                filter.rule_mode = match token.value.to_lowercase().as_str() {
+                    "ordered" => RuleMode::Ordered,
+                    "all" => RuleMode::All,
+                    _ => return Err(token.error("值应为 ordered 或 all")),
+                };
+            },
+            Some("hashalgo") => {
+                not_negated(&token)?;
+                filter.hash_algorithm = HashAlgorithm::from_name(&token.value.to_lowercase())
+                    .ok_or_else(|| token.error("值应为 xxh3、sha1、sha256、sha512 或 md5"))?;
+            },
+            Some("links") => {
+                not_negated(&token)?;
+                filter.symlink_mode = SymlinkMode::from_name(&token.value.to_lowercase())
+                    .ok_or_else(|| token.error("值应为 skip、list 或 follow"))?;
+            },
+            Some("kind") => {
+                not_negated(&token)?;
+                filter.result_kind = match token.value.to_lowercase().as_str() {
+                    "file" | "files" => ResultKind::Files,
+                    "dir" | "dirs" | "folder" | "folders" => ResultKind::Directories,
+                    "both" | "all" => ResultKind::Both,
+                    _ => return Err(token.error("值应为 files、dirs 或 both")),
+                };
+            },
+            Some(key) => {
+                return Err(token.error(format!("未知的条件类型 {}:，含有冒号的文字请用引号括起来", key)));
+            },
+            None => {}, // 普通文字在上面作为name:处理
+        }
+    }
+    // 没有正则规则时组合方式没有意义，恢复为默认值
+    if filter.regex_rules.is_empty() {
+        filter.rule_mode = RuleMode::default();
+    }
+    Ok(filter)
+}
+
+// 文字类条件: 条件类型、规则目标(None表示文件内容)、值是否为普通文字
+const TEXT_KEYS: [(&str, Option<RuleTarget>, bool); 8] = [
+    ("name", Some(RuleTarget::Name), true),
+    ("regex", Some(RuleTarget::Name), false),
+    ("pathregex", Some(RuleTarget::Path), false),
+    ("parent", Some(RuleTarget::Parent), true),
+    ("parentregex", Some(RuleTarget::Parent), false),
+    ("extregex", Some(RuleTarget::Extension), false),
+    ("content", None, true),
+    ("contentregex", None, false),
+];
+
+// 查找文字类条件，没有条件类型的普通文字相当于name:；条件类型前的i表示忽略大小写
+fn text_key(key: Option<&str>) -> Option<(Option<RuleTarget>, bool, bool)> {
+    let lookup = |key: &str| TEXT_KEYS.iter()
+        .find(|(name, _, _)| *name == key)
+        .map(|(_, target, literal)| (*target, *literal));
+    let Some(key) = key else {
+        return Some((Some(RuleTarget::Name), true, false));
+    };
+    if let Some((target, literal)) = lookup(key) {
+        return Some((target, literal, false));
+    }
+    key.strip_prefix('i')
+        .and_then(lookup)
+        .map(|(target, literal)| (target, literal, true))
+}
+
+// 名称、路径、文件夹名和扩展名条件按出现的顺序加入正则规则，按顺序匹配时顺序决定优先级
+fn add_rule(filter: &mut SearchFilter, token: &Token, pattern: String, target: RuleTarget, case_insensitive: bool) -> Result<(), QueryError> {
+    let mut rule = if token.negated { RegexRule::exclude(&pattern, target) } else { RegexRule::include(&pattern, target) };
+    rule.case_insensitive = case_insensitive;
+    rule.validate().map_err(|e| token.error(format!("无效的正则表达式: {}", e)))?;
+    filter.regex_rules.push(rule);
+    Ok(())
+}
+
+// 内容条件只能有一个
+fn set_content(filter: &mut SearchFilter, seen: &mut Seen, token: &Token, pattern: String, case_insensitive: bool) -> Result<(), QueryError> {
+    if std::mem::replace(&mut seen.content, true) {
+        return Err(token.error("内容条件只能出现一个"));
+    }
+    let pattern = if case_insensitive { format!("(?i){}", pattern) } else { pattern };
+    let regex = Regex::new(&pattern).map_err(|e| token.error(format!("无效的正则表达式: {}", e)))?;
+    filter.content_pattern = Some(regex);
+    filter.content_invert = token.negated;
+    Ok(())
+}
+
+// 通配符中有特殊含义的字符(","在{a,b}中分隔备选项)
+const GLOB_SPECIAL: &[char] = &['*', '?', '[', ']', '{', '}', ',', '\\'];
+
+// path:的值按字面匹配，其中的特殊字符放进字符类中转义，如[*]只匹配*
+// 不用反斜杠转义，因为globset在Windows上默认把反斜杠当作普通字符
+fn escape_glob(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if GLOB_SPECIAL.contains(&c) {
+            escaped.push('[');
+            escaped.push(c);
+            escaped.push(']');
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+// escape_glob的逆操作，含有没有转义的特殊字符时不是path:生成的，返回None
+fn unescape_glob(pattern: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '[' {
+            let escaped = chars.next().filter(|c| GLOB_SPECIAL.contains(c))?;
+            if chars.next() != Some(']') {
+                return None;
+            }
+            text.push(escaped);
+        } else if GLOB_SPECIAL.contains(&c) {
+            return None;
+        } else {
+            text.push(c);
+        }
+    }
+    Some(text)
+}
+
+fn add_glob(filter: &mut SearchFilter, token: &Token, pattern: String) -> Result<(), QueryError> {
+    let patterns = vec![pattern];
+    validate_globs(&patterns).map_err(|e| token.error(format!("无效的通配符: {}", e)))?;
+    if token.negated {
+        filter.exclude_globs.extend(patterns);
+    } else {
+        filter.include_globs.extend(patterns);
+    }
+    Ok(())
+}
+
+fn not_negated(token: &Token) -> Result<(), QueryError> {
+    if token.negated {
+        return Err(token.error("该条件不能取反"));
+    }
+    Ok(())
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "yes" | "y" | "true" | "on" | "1" => Some(true),
+        "no" | "n" | "false" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+// 解析日期，支持 2024-01-31 和 2024/1/31
+fn parse_date(text: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(text, "%Y/%m/%d"))
+        .map_err(|_| format!("无效的日期 {}，应为 年-月-日", text))
+}
+
+fn specific(minimum: NaiveDate, maximum: NaiveDate) -> DateLimitType {
+    DateLimitType::Specific {
+        minimum_year: minimum.year(),
+        minimum_month: minimum.month(),
+        minimum_day: minimum.day(),
+        maximum_year: maximum.year(),
+        maximum_month: maximum.month(),
+        maximum_day: maximum.day(),
+    }
+}
+
+// 解析日期条件，newer为false时表示在该时间范围以外(更旧)
+fn parse_date_limit(value: &str, newer: bool) -> Result<DateLimitType, String> {
+    let value = value.to_lowercase();
+    match value.as_str() {
+        "lastweek" | "pastweek" => return Ok(DateLimitType::Weeks(1, newer)),
+        "lastmonth" | "pastmonth" => return Ok(DateLimitType::Months(1, newer)),
+        "lastyear" | "pastyear" => return Ok(DateLimitType::Years(1, newer)),
+        _ => {},
+    }
+
+    if let Some(rest) = value.strip_prefix("last").or_else(|| value.strip_prefix("past")) {
+        let split = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (number, unit) = rest.split_at(split);
+        let number: i32 = match number.parse() {
+            Ok(number) if number >= 0 => number,
+            _ => return Err(format!("无效的数量 {}", number)),
+        };
+        return match unit {
+            "d" | "day" | "days" => Ok(DateLimitType::Days(number, newer)),
+            "w" | "week" | "weeks" => Ok(DateLimitType::Weeks(number, newer)),
+            "m" | "month" | "months" => Ok(DateLimitType::Months(number, newer)),
+            "y" | "year" | "years" => Ok(DateLimitType::Years(number, newer)),
+            _ => Err(format!("未知的时间单位 {}，应为 days、weeks、months 或 years", unit)),
+        };
+    }
+
+    // 以下都是具体的日期范围
+    if !newer {
+        return Err("具体的日期范围不能取反".to_string());
+    }
+    let today = Local::now().date_naive();
+    match value.as_str() {
+        "today" => return Ok(specific(today, today)),
+        "yesterday" => {
+            let yesterday = today.pred_opt().unwrap_or(today);
+            return Ok(specific(yesterday, yesterday));
+        },
+        _ => {},
+    }
+    if let Some((minimum, maximum)) = value.split_once("..") {
+        let minimum = if minimum.is_empty() { NaiveDate::from_ymd_opt(1970, 1, 1).unwrap() } else { parse_date(minimum)? };
+        let maximum = if maximum.is_empty() { NaiveDate::from_ymd_opt(9999, 12, 31).unwrap() } else { parse_date(maximum)? };
+        if maximum < minimum {
+            return Err("开始日期晚于结束日期".to_string());
+        }
+        return Ok(specific(minimum, maximum));
+    }
+    let date = parse_date(&value)?;
+    Ok(specific(date, date))
+}
+
+// 相对时间，例如 lastweek、last3days
+fn relative_date(value: i32, unit: &str) -> String {
+    if value == 1 && unit != "day" {
+        format!("last{}", unit)
+    } else {
+        format!("last{}{}s", value, unit)
+    }
+}
+
+// 值中的双引号写作\"，含有空白时加上引号
+// 结尾的反斜杠放在引号外，以免和闭合的引号组成\"
+fn quote(value: &str) -> String {
+    let escaped = value.replace('"', "\\\"");
+    if value.is_empty() || value.contains(char::is_whitespace) {
+        let body = escaped.trim_end_matches('\\');
+        format!("\"{}\"{}", body, &escaped[body.len()..])
+    } else {
+        escaped
+    }
+}
+
+// 正则表达式是否只是普通文字(不含任何特殊字符)
+fn is_literal(pattern: &str) -> bool {
+    regex::escape(pattern) == pattern
+}
+
+// 把过滤条件转换为等价的查询
+pub fn to_query(filter: &SearchFilter) -> String {
+    let defaults = SearchFilter::default();
+    let mut terms: Vec<String> = Vec::new();
+    let not = |negated: bool| if negated { "!" } else { "" };
+
+    for rule in filter.regex_rules.iter().filter(|rule| !rule.pattern.is_empty()) {
+        let key = match (rule.target, is_literal(&rule.pattern)) {
+            (RuleTarget::Name, true) => "name",
+            (RuleTarget::Name, false) => "regex",
+            (RuleTarget::Path, _) => "pathregex",
+            (RuleTarget::Parent, true) => "parent",
+            (RuleTarget::Parent, false) => "parentregex",
+            (RuleTarget::Extension, _) => "extregex",
+        };
+        let case = if rule.case_insensitive { "i" } else { "" };
+        terms.push(format!("{}{}{}:{}", not(rule.exclude), case, key, quote(&rule.pattern)));
+    }
+    if filter.rule_mode == RuleMode::Ordered && filter.regex_rules.iter().any(|rule| !rule.pattern.is_empty()) {
+        terms.push("rules:ordered".to_string());
+    }
+    if let Some(regex) = &filter.content_pattern {
+        let (pattern, case) = match regex.as_str().strip_prefix("(?i)") {
+            Some(pattern) => (pattern, "i"),
+            None => (regex.as_str(), ""),
+        };
+        let key = if is_literal(pattern) { "content" } else { "contentregex" };
+        terms.push(format!("{}{}{}:{}", not(filter.content_invert), case, key, quote(pattern)));
+    }
+
+    if !filter.include_extensions.is_empty() {
+        terms.push(format!("ext:{}", quote(&filter.include_extensions.join(","))));
+    }
+    if !filter.exclude_extensions.is_empty() {
+        terms.push(format!("!ext:{}", quote(&filter.exclude_extensions.join(","))));
+    }
+    let categories: Vec<&str> = filter.categories.iter()
+        .filter(|category| category.selected)
+        .map(|category| category.name.as_str())
+        .collect();
+    if !categories.is_empty() {
+        terms.push(format!("type:{}", quote(&categories.join(","))));
+    }
+
+    let size = format_size_range(filter.min_file_size, filter.max_file_size);
+    if !size.is_empty() {
+        terms.push(format!("size:{}", size));
+    }
+
+    let date_key = match filter.time_field {
+        TimeField::Modified => "dm",
+        TimeField::Created => "dc",
+        TimeField::Accessed => "da",
+    };
+    match &filter.date_limit {
+        DateLimitType::None => {},
+        // 负数的相对时间不限制时间(见SearchFilter::to_search_options)，也无法写成查询
+        DateLimitType::Days(value, _) | DateLimitType::Weeks(value, _) | DateLimitType::Months(value, _) | DateLimitType::Years(value, _) if *value < 0 => {},
+        DateLimitType::Days(value, newer) => terms.push(format!("{}{}:{}", not(!newer), date_key, relative_date(*value, "day"))),
+        DateLimitType::Weeks(value, newer) => terms.push(format!("{}{}:{}", not(!newer), date_key, relative_date(*value, "week"))),
+        DateLimitType::Months(value, newer) => terms.push(format!("{}{}:{}", not(!newer), date_key, relative_date(*value, "month"))),
+        DateLimitType::Years(value, newer) => terms.push(format!("{}{}:{}", not(!newer), date_key, relative_date(*value, "year"))),
+        DateLimitType::Specific { minimum_year, minimum_month, minimum_day, maximum_year, maximum_month, maximum_day } => {
+            let minimum = format!("{:04}-{:02}-{:02}", minimum_year, minimum_month, minimum_day);
+            let maximum = format!("{:04}-{:02}-{:02}", maximum_year, maximum_month, maximum_day);
+            if minimum == maximum {
+                terms.push(format!("{}:{}", date_key, minimum));
+            } else {
+                terms.push(format!("{}:{}..{}", date_key, minimum, maximum));
+            }
+        },
+    }
+
+    // path:生成的通配符还原为path:
+    for pattern in &filter.include_globs {
+        let text = pattern.strip_prefix("{**/*")
+            .and_then(|rest| rest.split_once("*,**/*"))
+            .filter(|(text, rest)| *rest == format!("{}*/**}}", text))
+            .and_then(|(text, _)| unescape_glob(text));
+        match text {
+            Some(text) => terms.push(format!("path:{}", quote(&text))),
+            None => terms.push(format!("glob:{}", quote(pattern))),
+        }
+    }
+    for pattern in &filter.exclude_globs {
+        let text = pattern.strip_prefix("**/*")
+            .and_then(|rest| rest.strip_suffix('*'))
+            .and_then(unescape_glob)
+            .filter(|text| !text.contains('/'));
+        match text {
+            Some(text) => terms.push(format!("!path:{}", quote(&text))),
+            None => terms.push(format!("!glob:{}", quote(pattern))),
+        }
+    }
+    for directory in &filter.excluded_directories {
+        terms.push(format!("!dir:{}", quote(directory)));
+    }
+
+    let switches = [
+        ("hidden", filter.search_hidden_files, defaults.search_hidden_files),
+        ("hiddendir", filter.search_hidden_folders, defaults.search_hidden_folders),
+        ("readonly", filter.search_readonly_files, defaults.search_readonly_files),
+        ("system", filter.search_system_files, defaults.search_system_files),
+        ("ignore", filter.respect_ignore_files, defaults.respect_ignore_files),
+        ("hash", filter.record_hash, defaults.record_hash),
+        ("empty", filter.find_empty, defaults.find_empty),
+        ("junk", filter.junk_as_empty, defaults.junk_as_empty),
+    ];
+    for (key, value, default) in switches {
+        if value != default {
+            terms.push(format!("{}:{}", key, if value { "yes" } else { "no" }));
+        }
+    }
+    if filter.hash_algorithm != defaults.hash_algorithm {
+        terms.push(format!("hashalgo:{}", filter.hash_algorithm.as_str()));
+    }
+    if filter.symlink_mode != defaults.symlink_mode {
+        terms.push(format!("links:{}", filter.symlink_mode.as_str()));
+    }
+    if filter.result_kind != defaults.result_kind {
+        terms.push(format!("kind:{}", filter.result_kind.as_str()));
+    }
+
+    terms.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::search_file::glob::build_glob_set;
+    use crate::search_file::rules::RuleSet;
+
+    fn parse(query: &str) -> SearchFilter {
+        parse_query(query, &SearchFilter::default()).unwrap()
+    }
+
+    fn parse_error(query: &str) -> QueryError {
+        parse_query(query, &SearchFilter::default()).err().unwrap()
+    }
+
+    fn keeps(filter: &SearchFilter, path: &str) -> bool {
+        RuleSet::new(&filter.regex_rules, filter.rule_mode).unwrap().matches(Path::new(path))
+    }
+
+    #[test]
+    fn query_round_trips() {
+        for query in [
+            "name:readme !iparentregex:^tests?$ ext:rs,toml size:>1MB !dm:lastweek",
+            "regex:^a.*b$ extregex:^rs$ rules:ordered !content:TODO",
+            "!ext:tmp,bak type:图片 dc:2024-01-01..2024-06-30 !dir:target hidden:no system:no",
+            "glob:src/**/*.rs path:docs !glob:**/*.min.js !path:build hashalgo:md5 links:skip kind:both",
+            "name:\"my file\" !name:\"say \\\"hi\\\"\" regex:\"dir name\"\\\\",
+        ] {
+            assert_eq!(to_query(&parse(query)), query);
+        }
+    }
+
+    #[test]
+    fn path_values_are_matched_literally() {
+        let filter = parse("path:a*b,c !path:[x]");
+        assert_eq!(to_query(&filter), "path:a*b,c !path:[x]");
+        let include = build_glob_set(&filter.include_globs);
+        assert!(include.is_match("docs/a*b,c.txt"));
+        assert!(include.is_match("a*b,c/readme.md"));
+        assert!(!include.is_match("docs/axb,c.txt"));
+        assert!(!include.is_match("docs/c.txt"));
+        let exclude = build_glob_set(&filter.exclude_globs);
+        assert!(exclude.is_match("build/[x].o"));
+        assert!(!exclude.is_match("build/x.o"));
+
+        for value in ["{a}", "?", "a\\b", "]"] {
+            let query = format!("path:{}", value);
+            let filter = parse(&query);
+            assert!(build_glob_set(&filter.include_globs).is_match(format!("dir/{}", value)), "{}", query);
+            assert_eq!(to_query(&filter), query);
+        }
+    }
+
+    #[test]
+    fn zero_relative_dates_round_trip() {
+        // 日期面板可以选择0天/周/月/年
+        for limit in [DateLimitType::Days(0, true), DateLimitType::Weeks(0, false), DateLimitType::Months(0, true), DateLimitType::Years(0, true)] {
+            let filter = SearchFilter { date_limit: limit.clone(), ..SearchFilter::default() };
+            let query = to_query(&filter);
+            assert_eq!(parse(&query).date_limit, limit, "{}", query);
+        }
+        assert_eq!(parse("dm:last0days").date_limit, DateLimitType::Days(0, true));
+        assert_eq!(parse_error("dm:last-1days").position, 1);
+
+        let filter = SearchFilter { date_limit: DateLimitType::Days(-1, true), ..SearchFilter::default() };
+        assert_eq!(to_query(&filter), "");
+    }
+
+    #[test]
+    fn text_terms_must_all_match() {
+        let filter = parse("foo bar !baz");
+        assert_eq!(filter.rule_mode, RuleMode::All);
+        assert!(keeps(&filter, "/dir/foo_bar.txt"));
+        assert!(!keeps(&filter, "/dir/foo.txt"));
+        assert!(!keeps(&filter, "/dir/bar.txt"));
+        assert!(!keeps(&filter, "/dir/foo_bar_baz.txt"));
+        assert_eq!(to_query(&filter), "name:foo name:bar !name:baz");
+    }
+
+    #[test]
+    fn ordered_rules_let_the_last_match_win() {
+        let filter = parse("name:foo name:bar rules:ordered");
+        assert_eq!(filter.rule_mode, RuleMode::Ordered);
+        assert!(keeps(&filter, "/dir/foo.txt"));
+        assert!(keeps(&filter, "/dir/bar.txt"));
+
+        // 没有正则规则时不输出rules:
+        let filter = parse("rules:ordered ext:rs");
+        assert_eq!(filter.rule_mode, RuleMode::default());
+        assert_eq!(to_query(&filter), "ext:rs");
+    }
+
+    #[test]
+    fn escaped_quotes_are_part_of_the_value() {
+        let filter = parse("name:a\\\"b \"c \\\"d\\\"\"");
+        let patterns: Vec<&str> = filter.regex_rules.iter().map(|rule| rule.pattern.as_str()).collect();
+        assert_eq!(patterns, ["a\"b", "c \"d\""]);
+        assert_eq!(quote("a\"b"), "a\\\"b");
+        assert_eq!(quote("c \"d\""), "\"c \\\"d\\\"\"");
+        assert_eq!(quote("a b\\"), "\"a b\"\\");
+        assert_eq!(quote(""), "\"\"");
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_term() {
+        let error = parse_error("ext:rs name:\"abc");
+        assert_eq!((error.position, error.token.as_str()), (8, "name:\"abc"));
+
+        let error = parse_error("ext:rs foo:bar");
+        assert_eq!((error.position, error.token.as_str()), (8, "foo:bar"));
+
+        // 位置按字符而不是字节计算
+        let error = parse_error("文件  size:abc");
+        assert_eq!((error.position, error.token.as_str()), (5, "size:abc"));
+
+        let error = parse_error("name:\\\"abc\" ext:rs");
+        assert_eq!(error.position, 1);
+
+        let error = parse_error("size:1MB size:2MB");
+        assert_eq!(error.position, 10);
+
+        let error = parse_error("name:a rules:any");
+        assert_eq!(error.position, 8);
+    }
+}
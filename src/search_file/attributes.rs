@@ -0,0 +1,80 @@
+// 跨平台的文件属性判断，供过滤隐藏/只读/系统文件使用
+// Windows下读取文件属性位；Unix下按照约定推断:
+// 以"."开头的文件为隐藏文件，当前用户没有写权限的文件为只读文件，设备、套接字、管道等特殊文件为系统文件
+
+use std::fs;
+use std::path::Path;
+
+#[cfg(windows)]
+const FILE_ATTRIBUTE_READONLY: u32 = 0x00000001;
+#[cfg(windows)]
+const FILE_ATTRIBUTE_HIDDEN: u32 = 0x00000002;
+#[cfg(windows)]
+const FILE_ATTRIBUTE_SYSTEM: u32 = 0x00000004;
+
+// 文件名是否以"."开头
+fn has_dot_name(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.starts_with('.'))
+        .unwrap_or(false)
+}
+
+// 是否为隐藏文件(或目录)
+// 以"."开头的名称在两个平台上都视为隐藏，Windows下带有隐藏属性的文件也是隐藏文件
+#[cfg(windows)]
+pub fn is_hidden(path: &Path, metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0 || has_dot_name(path)
+}
+
+#[cfg(unix)]
+pub fn is_hidden(path: &Path, _metadata: &fs::Metadata) -> bool {
+    has_dot_name(path)
+}
+
+// 是否为只读文件
+#[cfg(windows)]
+pub fn is_readonly(_path: &Path, metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    metadata.file_attributes() & FILE_ATTRIBUTE_READONLY != 0
+}
+
+// Unix下以当前(有效)用户能否写入为准，而不是只看权限位，这样属组、ACL、只读挂载都能正确处理
+#[cfg(unix)]
+pub fn is_readonly(path: &Path, _metadata: &fs::Metadata) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: c_path是以NUL结尾的有效字符串，faccessat只读取它
+    unsafe { libc::faccessat(libc::AT_FDCWD, c_path.as_ptr(), libc::W_OK, libc::AT_EACCESS) != 0 }
+}
+
+// 是否为系统文件
+#[cfg(windows)]
+pub fn is_system(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    metadata.file_attributes() & FILE_ATTRIBUTE_SYSTEM != 0
+}
+
+// Unix下没有系统文件属性，把设备文件、套接字和命名管道视为系统文件
+#[cfg(unix)]
+pub fn is_system(metadata: &fs::Metadata) -> bool {
+    is_special(metadata)
+}
+
+// 是否为特殊文件(设备、套接字、管道等)，这类文件没有可以读取的普通内容，不能计算哈希
+#[cfg(unix)]
+pub fn is_special(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    let file_type = metadata.file_type();
+    file_type.is_block_device() || file_type.is_char_device() || file_type.is_fifo() || file_type.is_socket()
+}
+
+#[cfg(windows)]
+pub fn is_special(_metadata: &fs::Metadata) -> bool {
+    false
+}
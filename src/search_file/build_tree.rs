@@ -1,7 +1,6 @@
 
 use std::path::PathBuf;
 
-use crate::search_file;
 use crate::search_file::SingleFileInformations;
 
 struct FolderInformations {
@@ -16,11 +15,8 @@ fn build_tree(files: &Vec<SingleFileInformations>) -> Vec<FolderInformations> {
     let mut current:usize=0;
     tree.push(FolderInformations { foldname: String::new(), father: None, childs: Vec::new() });
     for file in files {
-        let mut file_path = file.path.clone();
-        while match file_path.strip_prefix(&current_path) {
-            Err(e) => true,
-            Ok(stripped) => false,
-        } {
+        let file_path = file.path.clone();
+        while file_path.strip_prefix(&current_path).is_err() {
             current = tree[current].father.unwrap_or(0);
             current_path.pop();
         }
@@ -35,7 +31,7 @@ fn build_tree(files: &Vec<SingleFileInformations>) -> Vec<FolderInformations> {
                 continue; // Skip empty parts
             }
             let mut found = false;
-            for (i, folder) in tree[current].childs.iter().enumerate() {
+            for folder in tree[current].childs.iter() {
                 if tree[*folder].foldname == part {
                     current = *folder;
                     found = true;
@@ -48,7 +44,7 @@ fn build_tree(files: &Vec<SingleFileInformations>) -> Vec<FolderInformations> {
                     father: Some(current),
                     childs: Vec::new(),
                 });
-                let mut new_folder_index:usize = (tree.len() as usize) - 1;
+                let new_folder_index:usize = tree.len() - 1;
                 tree[current].childs.push(new_folder_index);
                 current = new_folder_index;
             }
@@ -58,7 +54,7 @@ fn build_tree(files: &Vec<SingleFileInformations>) -> Vec<FolderInformations> {
     tree
 }
 
-fn print_tree(tree: &Vec<FolderInformations>) -> String {
+fn print_tree(tree: &[FolderInformations]) -> String {
     let mut output = String::new();
     output.push_str("Tree structure:\n");
     let mut depth : Vec<u32> = vec![0; tree.len()];
@@ -81,7 +77,7 @@ fn print_tree(tree: &Vec<FolderInformations>) -> String {
                     output.push_str(" ├──");
                 }
             } else {
-                if(ended[i as usize]) {
+                if ended[i as usize] {
                     output.push_str("    ");
                 } else {
                     output.push_str(" │  ");
@@ -98,9 +94,8 @@ fn print_tree(tree: &Vec<FolderInformations>) -> String {
 }
 
 pub fn get_tree(files: &Vec<SingleFileInformations>) -> String {
-    let tree: Vec<FolderInformations> = build_tree(&files);
-    let output = print_tree(&tree);
-    output
+    let tree: Vec<FolderInformations> = build_tree(files);
+    print_tree(&tree)
     // for (id, folder) in tree.iter().enumerate() {
     //     println!("id:{}, name: {}", id, folder.foldname);
     //     for child in &folder.childs {
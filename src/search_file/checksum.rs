@@ -0,0 +1,414 @@
+// 哈希算法和校验文件
+// FileHasher把各种哈希算法包装成同一个接口，搜索、查找重复文件和校验文件都通过它计算哈希值
+// 校验文件使用sha256sum/md5sum等工具的格式，每行一个文件:
+//   <哈希值>  <路径>        文本模式(两个空格)
+//   <哈希值> *<路径>        二进制模式
+//   \<哈希值>  <路径>       路径中含有反斜杠或换行符时，行首加反斜杠，路径中的\\和\n为转义
+//   SHA256 (<路径>) = <哈希值>   --tag生成的BSD格式(只读取)
+// 相对路径相对于校验文件所在的目录
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use xxhash_rust::xxh3::Xxh3;
+
+use super::settings::{HashAlgorithm, TimeField};
+use super::{CancelToken, SearchOperation, SearchReport, SingleFileInformations, hash_file, timestamp_seconds};
+
+/// 按HashAlgorithm选择的哈希计算器
+pub enum FileHasher {
+    Xxh3(Box<Xxh3>),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Md5(Md5),
+}
+
+impl FileHasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Xxh3 => FileHasher::Xxh3(Box::new(Xxh3::new())),
+            HashAlgorithm::Sha1 => FileHasher::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => FileHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => FileHasher::Sha512(Sha512::new()),
+            HashAlgorithm::Md5 => FileHasher::Md5(Md5::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            FileHasher::Xxh3(hasher) => hasher.update(data),
+            FileHasher::Sha1(hasher) => hasher.update(data),
+            FileHasher::Sha256(hasher) => hasher.update(data),
+            FileHasher::Sha512(hasher) => hasher.update(data),
+            FileHasher::Md5(hasher) => hasher.update(data),
+        }
+    }
+
+    // 小写十六进制的哈希值，XXH3使用128位的结果，与xxhsum -H2的输出相同
+    pub fn finish(self) -> String {
+        match self {
+            FileHasher::Xxh3(hasher) => format!("{:032x}", hasher.digest128()),
+            FileHasher::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            FileHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            FileHasher::Sha512(hasher) => format!("{:x}", hasher.finalize()),
+            FileHasher::Md5(hasher) => format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+impl HashAlgorithm {
+    // 从校验文件的文件名推断算法，如files.sha256、SHA1SUMS
+    pub fn from_manifest_name(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+        let from_name = |name: &str| match name {
+            "xxh128" | "xxh3" => Some(HashAlgorithm::Xxh3),
+            _ => HashAlgorithm::from_name(name),
+        };
+        extension.as_deref().and_then(from_name)
+            .or_else(|| name.strip_suffix("sums").and_then(from_name))
+    }
+
+    // 按十六进制哈希值的长度推断算法，32位的按MD5处理
+    pub fn from_hex_length(length: usize) -> Option<Self> {
+        match length {
+            32 => Some(HashAlgorithm::Md5),
+            40 => Some(HashAlgorithm::Sha1),
+            64 => Some(HashAlgorithm::Sha256),
+            128 => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    // BSD格式中的算法名
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "XXH128" => Some(HashAlgorithm::Xxh3),
+            "SHA1" => Some(HashAlgorithm::Sha1),
+            "SHA256" => Some(HashAlgorithm::Sha256),
+            "SHA512" => Some(HashAlgorithm::Sha512),
+            "MD5" => Some(HashAlgorithm::Md5),
+            _ => None,
+        }
+    }
+
+    // 十六进制哈希值的长度
+    pub fn hex_length(&self) -> usize {
+        match self {
+            HashAlgorithm::Xxh3 | HashAlgorithm::Md5 => 32,
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha512 => 128,
+        }
+    }
+}
+
+/// 校验文件中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub algorithm: HashAlgorithm,
+    pub hash: String, // 小写
+    pub path: PathBuf, // 已按校验文件所在的目录解析
+}
+
+// 解析校验文件的内容，返回各行的条目和无法解析的行数
+// 文本格式的行按文件名推断算法(default)，推断不出时按哈希值的长度判断
+pub fn parse_manifest(text: &str, directory: &Path, default: Option<HashAlgorithm>) -> (Vec<ManifestEntry>, usize) {
+    let mut entries = Vec::new();
+    let mut malformed = 0;
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_line(line, default) {
+            Some((algorithm, hash, path)) => entries.push(ManifestEntry {
+                algorithm,
+                hash: hash.to_lowercase(),
+                path: directory.join(path),
+            }),
+            None => malformed += 1,
+        }
+    }
+    (entries, malformed)
+}
+
+// 解析一行，返回算法、哈希值和路径
+fn parse_line(line: &str, default: Option<HashAlgorithm>) -> Option<(HashAlgorithm, String, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let is_hex = |hash: &str| !hash.is_empty() && hash.bytes().all(|byte| byte.is_ascii_hexdigit());
+
+    // BSD格式: ALGORITHM (path) = hash
+    let tagged = line.split_once(" (").and_then(|(tag, rest)| {
+        let algorithm = HashAlgorithm::from_tag(tag)?;
+        let (path, hash) = rest.rsplit_once(") = ")?;
+        (is_hex(hash) && hash.len() == algorithm.hex_length()).then_some((algorithm, hash, path))
+    });
+    if let Some((algorithm, hash, path)) = tagged {
+        return Some((algorithm, hash.to_string(), unescape(path, escaped)?));
+    }
+
+    // GNU格式: hash  path 或 hash *path
+    let (hash, rest) = line.split_once(' ')?;
+    if !is_hex(hash) {
+        return None;
+    }
+    let algorithm = default.or_else(|| HashAlgorithm::from_hex_length(hash.len()))?;
+    if hash.len() != algorithm.hex_length() {
+        return None;
+    }
+    let path = rest.strip_prefix([' ', '*']).unwrap_or(rest);
+    if path.is_empty() {
+        return None;
+    }
+    Some((algorithm, hash.to_string(), unescape(path, escaped)?))
+}
+
+// 还原行首有反斜杠的行中转义的路径
+fn unescape(path: &str, escaped: bool) -> Option<String> {
+    if !escaped {
+        return Some(path.to_string());
+    }
+    let mut result = String::with_capacity(path.len());
+    let mut characters = path.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            result.push(character);
+            continue;
+        }
+        match characters.next()? {
+            '\\' => result.push('\\'),
+            'n' => result.push('\n'),
+            'r' => result.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+// 生成一行，路径中含有反斜杠或换行符时转义
+fn format_line(hash: &str, path: &str) -> String {
+    if path.contains(['\\', '\n', '\r']) {
+        let path = path.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r");
+        format!("\\{}  {}\n", hash, path)
+    } else {
+        format!("{}  {}\n", hash, path)
+    }
+}
+
+// 校验文件中记录的路径: 在校验文件所在目录之内的用/分隔的相对路径，否则为完整路径
+fn manifest_path(path: &Path, directory: &Path) -> String {
+    match path.strip_prefix(directory) {
+        Ok(relative) if !relative.as_os_str().is_empty() => relative.components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        _ => path.to_string_lossy().to_string(),
+    }
+}
+
+/// 导出校验文件的结果
+#[derive(Debug, Default)]
+pub struct ManifestExport {
+    pub written: usize, // 写入的文件数量
+    pub report: SearchReport, // 无法计算哈希的文件
+}
+
+// 把files中的文件以algorithm的哈希值写入校验文件manifest，目录不写入
+// 已经用同一算法记录了哈希值的文件直接使用记录的值，其他的重新计算
+// 被取消时不写入文件，report.cancelled为true
+pub fn export_manifest(files: &[SingleFileInformations], manifest: &Path, algorithm: HashAlgorithm, cancel: &CancelToken) -> io::Result<ManifestExport> {
+    let directory = manifest.parent().unwrap_or(Path::new(""));
+    let mut export = ManifestExport::default();
+    let mut text = String::new();
+    for file in files.iter().filter(|file| !file.is_dir) {
+        let hash = if !file.hash.is_empty() && file.hash_algorithm == algorithm {
+            file.hash.clone()
+        } else {
+            match hash_file(&file.path, algorithm, cancel) {
+                Ok(hash) => hash,
+                Err(_) if cancel.is_cancelled() => break,
+                Err(e) => {
+                    export.report.record(&file.path, SearchOperation::Hash, &e);
+                    continue;
+                }
+            }
+        };
+        text.push_str(&format_line(&hash, &manifest_path(&file.path, directory)));
+        export.written += 1;
+    }
+    if cancel.is_cancelled() {
+        export.report.cancelled = true;
+        return Ok(export);
+    }
+    fs::write(manifest, text)?;
+    Ok(export)
+}
+
+/// 导入校验文件的结果
+#[derive(Debug, Default)]
+pub struct ManifestImport {
+    pub files: Vec<SingleFileInformations>, // 校验文件中列出且存在的文件，hash为文件当前内容的哈希值
+    pub matched: usize, // 哈希值与校验文件一致的文件数量
+    pub mismatched: Vec<PathBuf>, // 内容已经改变的文件
+    pub missing: Vec<PathBuf>, // 已经不存在的文件
+    pub malformed: usize, // 无法解析的行数
+    pub report: SearchReport, // 无法读取的文件
+}
+
+// 读取校验文件manifest并逐个校验其中的文件
+// 被取消时返回已经校验的部分，report.cancelled为true
+pub fn import_manifest(manifest: &Path, cancel: &CancelToken) -> io::Result<ManifestImport> {
+    let text = fs::read_to_string(manifest)?;
+    let directory = manifest.parent().unwrap_or(Path::new(""));
+    let (entries, malformed) = parse_manifest(&text, directory, HashAlgorithm::from_manifest_name(manifest));
+    let mut import = ManifestImport { malformed, ..Default::default() };
+    for entry in entries {
+        if cancel.is_cancelled() {
+            import.report.cancelled = true;
+            break;
+        }
+        let metadata = match fs::metadata(&entry.path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                import.missing.push(entry.path);
+                continue;
+            }
+            Err(e) => {
+                import.report.record(&entry.path, SearchOperation::Metadata, &e);
+                continue;
+            }
+        };
+        let hash = match hash_file(&entry.path, entry.algorithm, cancel) {
+            Ok(hash) => hash,
+            Err(_) if cancel.is_cancelled() => {
+                import.report.cancelled = true;
+                break;
+            }
+            Err(e) => {
+                import.report.record(&entry.path, SearchOperation::Hash, &e);
+                continue;
+            }
+        };
+        if hash == entry.hash {
+            import.matched += 1;
+        } else {
+            import.mismatched.push(entry.path.clone());
+        }
+        import.files.push(SingleFileInformations {
+            name: entry.path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+            size: metadata.len(),
+            time: timestamp_seconds(&metadata, TimeField::Modified).unwrap_or(0),
+            created: timestamp_seconds(&metadata, TimeField::Created).ok(),
+            accessed: timestamp_seconds(&metadata, TimeField::Accessed).ok(),
+            hash,
+            hash_algorithm: entry.algorithm,
+            link_target: fs::read_link(&entry.path).ok(),
+            matches: Vec::new(),
+            is_dir: false,
+            file_count: 0,
+            path: entry.path,
+        });
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MD5: &str = "900150983cd24fb0d6963f7d28e17f72";
+    const SHA1: &str = "a9993e364706816aba3e25717850c26c9cd0d89d";
+    const SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn digest(algorithm: HashAlgorithm, data: &[u8]) -> String {
+        let mut hasher = FileHasher::new(algorithm);
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    #[test]
+    fn known_digests() {
+        // 与md5sum、sha1sum、sha256sum、sha512sum和xxhsum -H2对"abc"的输出相同
+        assert_eq!(digest(HashAlgorithm::Md5, b"abc"), MD5);
+        assert_eq!(digest(HashAlgorithm::Sha1, b"abc"), SHA1);
+        assert_eq!(digest(HashAlgorithm::Sha256, b"abc"), SHA256);
+        assert_eq!(digest(HashAlgorithm::Sha512, b"abc"), "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f");
+        assert_eq!(digest(HashAlgorithm::Xxh3, b"abc"), "06b05ab6733a618578af5f94892f3950");
+        assert_eq!(digest(HashAlgorithm::Xxh3, b""), "99aa06d3014798d86001c324468d497f");
+        for algorithm in HashAlgorithm::ALL {
+            assert_eq!(digest(algorithm, b"abc").len(), algorithm.hex_length());
+        }
+    }
+
+    #[test]
+    fn algorithm_from_manifest_name() {
+        let detect = |name: &str| HashAlgorithm::from_manifest_name(Path::new(name));
+        assert_eq!(detect("files.md5"), Some(HashAlgorithm::Md5));
+        assert_eq!(detect("/backup/files.SHA256"), Some(HashAlgorithm::Sha256));
+        assert_eq!(detect("release.sha512"), Some(HashAlgorithm::Sha512));
+        assert_eq!(detect("files.xxh128"), Some(HashAlgorithm::Xxh3));
+        assert_eq!(detect("SHA1SUMS"), Some(HashAlgorithm::Sha1));
+        assert_eq!(detect("MD5SUMS"), Some(HashAlgorithm::Md5));
+        assert_eq!(detect("sha256sums"), Some(HashAlgorithm::Sha256));
+        assert_eq!(detect("SHA256SUMS.txt"), None);
+        assert_eq!(detect("B2SUMS"), None);
+        assert_eq!(detect("checksums.txt"), None);
+    }
+
+    #[test]
+    fn parse_text_and_binary_lines() {
+        assert_eq!(parse_line(&format!("{}  a b.txt", MD5), None), Some((HashAlgorithm::Md5, MD5.to_string(), "a b.txt".to_string())));
+        assert_eq!(parse_line(&format!("{} *data.bin", SHA1), None), Some((HashAlgorithm::Sha1, SHA1.to_string(), "data.bin".to_string())));
+        // 只有第一个*是二进制模式的标记
+        assert_eq!(parse_line(&format!("{} **star", SHA256), None), Some((HashAlgorithm::Sha256, SHA256.to_string(), "*star".to_string())));
+        assert_eq!(parse_line(&format!("SHA256 (dir/x (1).txt) = {}", SHA256), None), Some((HashAlgorithm::Sha256, SHA256.to_string(), "dir/x (1).txt".to_string())));
+        // 32位的哈希值按文件名推断的算法解析
+        assert_eq!(parse_line(&format!("{}  a", MD5), Some(HashAlgorithm::Xxh3)).map(|(algorithm, _, _)| algorithm), Some(HashAlgorithm::Xxh3));
+    }
+
+    #[test]
+    fn parse_escaped_lines() {
+        assert_eq!(parse_line(&format!("\\{}  a\\\\b\\nc", MD5), None), Some((HashAlgorithm::Md5, MD5.to_string(), "a\\b\nc".to_string())));
+        assert_eq!(parse_line(&format!("\\SHA1 (a\\\\b) = {}", SHA1), None), Some((HashAlgorithm::Sha1, SHA1.to_string(), "a\\b".to_string())));
+        // 行首没有反斜杠时路径中的反斜杠不是转义
+        assert_eq!(parse_line(&format!("{}  a\\nb", MD5), None).map(|(_, _, path)| path), Some("a\\nb".to_string()));
+
+        assert_eq!(unescape("a\\\\b\\r\\n", true).as_deref(), Some("a\\b\r\n"));
+        assert_eq!(unescape("a\\x", true), None);
+        assert_eq!(unescape("a\\", true), None);
+
+        for path in ["plain.txt", "a\\b", "line\nbreak", "c:\\dir\\file"] {
+            let line = format_line(MD5, path);
+            assert_eq!(parse_line(line.trim_end_matches('\n'), None), Some((HashAlgorithm::Md5, MD5.to_string(), path.to_string())));
+        }
+    }
+
+    #[test]
+    fn malformed_lines() {
+        for (line, default) in [
+            (MD5.to_string(), None), // 没有路径
+            (format!("{}  ", MD5), None),
+            (format!("{}x  a", MD5), None), // 不是十六进制
+            (format!("{}  a", &MD5[1..]), None), // 长度不对应任何算法
+            (format!("{}  a", SHA1), Some(HashAlgorithm::Md5)), // 与校验文件的算法不符
+            (format!("SHA256 (a) = {}", SHA1), None),
+            (format!("\\{}  a\\q", MD5), None), // 无效的转义
+        ] {
+            assert_eq!(parse_line(&line, default), None, "{}", line);
+        }
+
+        let text = format!("# comment\n{}  a\r\n\nnot a checksum\n{} *b\n", MD5, MD5);
+        let (entries, malformed) = parse_manifest(&text, Path::new("/base"), None);
+        assert_eq!(malformed, 1);
+        let paths: Vec<&Path> = entries.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("/base/a"), Path::new("/base/b")]);
+    }
+}
@@ -0,0 +1,78 @@
+// 文件内容搜索
+// 按行匹配正则表达式，记录匹配的行号和该行的片段，相当于grep -rn
+// 二进制文件(含有NUL字节)直接跳过，每个文件最多读取options.content_scan_limit字节
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::CancelToken;
+
+// 每个文件最多记录的匹配行数，避免日志之类的大文件占用过多内存
+const MAX_MATCHES_PER_FILE: usize = 100;
+// 片段最多保留的字符数
+const MAX_SNIPPET_CHARS: usize = 200;
+
+/// 文件内容中的一处匹配
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentMatch {
+    pub line: u64, // 行号，从1开始
+    pub snippet: String, // 匹配行的内容(去掉首尾空白，过长时截断)
+}
+
+// 在文件内容中搜索正则表达式，返回所有匹配的行
+// 二进制文件返回空列表；搜索被取消时返回Interrupted错误
+pub fn search_content(path: &Path, regex: &Regex, max_bytes: u64, cancel: &CancelToken) -> io::Result<Vec<ContentMatch>> {
+    let file = fs::File::open(path)?;
+    let mut reader = BufReader::new(file.take(max_bytes));
+    let mut matches = Vec::new();
+    let mut line = Vec::new();
+    let mut line_number: u64 = 0;
+
+    loop {
+        if cancel.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "search cancelled"));
+        }
+        line.clear();
+        let bytes_read = match reader.read_until(b'\n', &mut line) {
+            Ok(bytes_read) => bytes_read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if bytes_read == 0 {
+            break;
+        }
+        line_number += 1;
+
+        // 文本文件中不会出现NUL字节，出现时认为是二进制文件
+        if line.contains(&0) {
+            return Ok(Vec::new());
+        }
+
+        if regex.is_match(&line) {
+            matches.push(ContentMatch {
+                line: line_number,
+                snippet: make_snippet(&line),
+            });
+            if matches.len() >= MAX_MATCHES_PER_FILE {
+                break;
+            }
+        }
+    }
+    Ok(matches)
+}
+
+// 把匹配的行转换为用于显示的片段，非UTF-8的内容按有损方式转换
+fn make_snippet(line: &[u8]) -> String {
+    let text = String::from_utf8_lossy(line);
+    let text = text.trim();
+    if text.chars().count() > MAX_SNIPPET_CHARS {
+        let mut snippet: String = text.chars().take(MAX_SNIPPET_CHARS).collect();
+        snippet.push('…');
+        snippet
+    } else {
+        text.to_string()
+    }
+}
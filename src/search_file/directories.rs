@@ -0,0 +1,101 @@
+// 作为搜索结果的目录: 统计目录中所有文件的总大小和数量
+// 统计时不跟随符号链接(与du相同)，无法读取的子目录按空目录计算
+// 统计一个目录时会顺带得到其中每个子目录的结果，记录在TotalsCache中，之后子目录也作为结果时不需要再次遍历
+// 查找空目录时，名称在junk列表中的文件(如Thumbs.db)单独计数，只有这类文件的目录也视为空目录
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::CancelToken;
+use super::empty::is_junk;
+
+/// 目录中所有文件的汇总(包括各层子目录)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirectoryTotals {
+    pub size: u64, // 文件的总大小
+    pub files: u64, // 文件数量，符号链接也算作文件
+    pub directories: u64, // 子目录数量
+    pub junk_files: u64, // 其中名称在junk列表中的文件数量(包含在files中)
+}
+
+impl DirectoryTotals {
+    // 除了junk之外没有任何文件
+    pub fn is_empty(&self) -> bool {
+        self.files == self.junk_files
+    }
+}
+
+/// 一次搜索中已经统计过的目录，由所有工作线程共享
+#[derive(Debug, Default)]
+pub struct TotalsCache {
+    totals: Mutex<HashMap<PathBuf, DirectoryTotals>>,
+    junk: Vec<String>, // 单独计数的文件名，不区分大小写
+}
+
+impl TotalsCache {
+    pub fn new(junk: Vec<String>) -> Self {
+        Self {
+            totals: Mutex::default(),
+            junk,
+        }
+    }
+
+    // 统计目录，目录本身无法读取时返回错误
+    // 搜索被取消时返回Interrupted，不完整的结果不会被记录
+    pub fn measure(&self, path: &Path, cancel: &CancelToken) -> io::Result<DirectoryTotals> {
+        if let Some(totals) = self.totals.lock().unwrap().get(path) {
+            return Ok(*totals);
+        }
+        let mut measured = Vec::new();
+        let totals = self.measure_into(path, cancel, &mut measured)?;
+        let mut cache = self.totals.lock().unwrap();
+        cache.extend(measured);
+        cache.insert(path.to_path_buf(), totals);
+        Ok(totals)
+    }
+
+    // 递归统计，子目录的结果放入measured
+    fn measure_into(&self, path: &Path, cancel: &CancelToken, measured: &mut Vec<(PathBuf, DirectoryTotals)>) -> io::Result<DirectoryTotals> {
+        let mut totals = DirectoryTotals::default();
+        for entry in fs::read_dir(path)? {
+            if cancel.is_cancelled() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "search cancelled"));
+            }
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                let child_path = entry.path();
+                let cached = self.totals.lock().unwrap().get(&child_path).copied();
+                let child = match cached {
+                    Some(child) => child,
+                    None => match self.measure_into(&child_path, cancel, measured) {
+                        Ok(child) => {
+                            measured.push((child_path, child));
+                            child
+                        },
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => return Err(e),
+                        Err(_) => DirectoryTotals::default(),
+                    },
+                };
+                totals.size += child.size;
+                totals.files += child.files;
+                totals.directories += child.directories + 1;
+                totals.junk_files += child.junk_files;
+            } else {
+                totals.files += 1;
+                if is_junk(&entry.file_name(), &self.junk) {
+                    totals.junk_files += 1;
+                }
+                totals.size += entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            }
+        }
+        Ok(totals)
+    }
+}
@@ -0,0 +1,613 @@
+// 重复文件查找
+// 分三步缩小范围，只有可能重复的文件才会被完整读取:
+// 1. 按文件大小分组，大小唯一的文件不可能有重复，不需要读取
+// 2. 对大小相同的文件读取开头和结尾各PARTIAL_HASH_BYTES字节，计算部分哈希
+// 3. 部分哈希也相同的文件才计算完整哈希
+// 部分哈希和完整哈希都使用调用者选择的算法，只用于比较时XXH3比SHA-256快得多
+// 不超过2*PARTIAL_HASH_BYTES的文件在第2步中已被完整读取，部分哈希就是完整哈希
+// 找到的重复文件以DuplicateGroup的形式列出每一个副本，由用户按规则或手动选择保留哪些，
+// 其余的移到回收站、移动到其他文件夹，或者替换为指向保留文件的链接
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use super::checksum::FileHasher;
+use super::settings::HashAlgorithm;
+use super::{CancelToken, SearchOperation, SearchReport, SingleFileInformations, directory_key, hash_file};
+
+// 部分哈希在文件开头和结尾各读取的字节数
+pub const PARTIAL_HASH_BYTES: u64 = 16 * 1024;
+
+/// 重复文件查找的进度，可在其他线程中随时读取
+#[derive(Debug, Default)]
+pub struct DuplicateProgress {
+    pub candidates: AtomicUsize, // 与其他文件大小相同、需要比较内容的文件数量
+    pub partial_hashed: AtomicUsize, // 已计算部分哈希的文件数量
+    pub full_hashed: AtomicUsize, // 已计算完整哈希的文件数量
+}
+
+/// 处理重复文件(移到回收站、移动、替换为链接)的进度，可在其他线程中随时读取
+#[derive(Debug, Default)]
+pub struct DuplicateActionProgress {
+    pub total: AtomicUsize, // 需要处理的文件数量
+    pub done: AtomicUsize, // 已处理(无论成功与否)的文件数量
+}
+
+// 找出files中内容相同的文件，返回每组重复文件在files中的下标(每组至少两个)
+// 重复文件用algorithm计算的完整哈希写入其hash字段；所有文件都已有同一算法的哈希值(搜索时记录了哈希)的一组直接按哈希值比较
+// 目录、空文件和符号链接不参与比较；同一个文件(如硬链接，或从多个搜索根目录找到的同一路径)只算一次
+// 无法读取的文件记录在report中；搜索被取消时返回空列表，report.cancelled为true
+// 各组按文件大小从大到小排列，组内按在files中的顺序排列
+pub fn find_duplicates(files: &mut [SingleFileInformations], algorithm: HashAlgorithm, threads: usize, progress: &DuplicateProgress, cancel: &CancelToken, report: &mut SearchReport) -> Vec<Vec<usize>> {
+    let mut groups = Vec::new();
+
+    // 第1步: 按大小分组
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        if file.is_dir || file.link_target.is_some() || file.size == 0 {
+            continue;
+        }
+        by_size.entry(file.size).or_default().push(index);
+    }
+
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for (_, indices) in by_size {
+        if indices.len() < 2 {
+            continue;
+        }
+        let indices: Vec<usize> = indices.into_iter()
+            .filter(|&index| {
+                let path = &files[index].path;
+                match fs::metadata(path) {
+                    Ok(metadata) => directory_key(path, &metadata).is_none_or(|key| seen.insert(key)),
+                    Err(e) => {
+                        report.record(path, SearchOperation::Metadata, &e);
+                        false
+                    }
+                }
+            })
+            .collect();
+        if indices.len() < 2 {
+            continue;
+        }
+        if indices.iter().all(|&index| !files[index].hash.is_empty() && files[index].hash_algorithm == algorithm) {
+            groups.extend(group_by(&indices, |index| (files[index].size, files[index].hash.clone())));
+        } else {
+            candidates.extend(indices);
+        }
+    }
+    progress.candidates.store(candidates.len(), Ordering::Relaxed);
+
+    // 第2步: 部分哈希
+    let partial = hash_in_parallel(files, &candidates, threads, cancel, report, |file| {
+        let hash = partial_hash(&file.path, file.size, algorithm, cancel);
+        progress.partial_hashed.fetch_add(1, Ordering::Relaxed);
+        hash
+    });
+    let mut full_candidates = Vec::new();
+    for group in group_by(&candidates_with_hash(&candidates, &partial), |index| (files[index].size, partial[&index].clone())) {
+        if files[group[0]].size <= 2 * PARTIAL_HASH_BYTES {
+            for &index in &group {
+                files[index].hash = partial[&index].clone();
+                files[index].hash_algorithm = algorithm;
+            }
+            groups.push(group);
+        } else {
+            full_candidates.extend(group);
+        }
+    }
+
+    // 第3步: 完整哈希
+    let full = hash_in_parallel(files, &full_candidates, threads, cancel, report, |file| {
+        let hash = hash_file(&file.path, algorithm, cancel);
+        progress.full_hashed.fetch_add(1, Ordering::Relaxed);
+        hash
+    });
+    for group in group_by(&candidates_with_hash(&full_candidates, &full), |index| (files[index].size, full[&index].clone())) {
+        for &index in &group {
+            files[index].hash = full[&index].clone();
+            files[index].hash_algorithm = algorithm;
+        }
+        groups.push(group);
+    }
+
+    if cancel.is_cancelled() {
+        report.cancelled = true;
+        return Vec::new();
+    }
+    for group in &mut groups {
+        group.sort_unstable();
+    }
+    groups.sort_by(|a, b| files[b[0]].size.cmp(&files[a[0]].size).then(a[0].cmp(&b[0])));
+    groups
+}
+
+// 按key分组，只保留至少有两个文件的组
+fn group_by<K: std::hash::Hash + Eq>(indices: &[usize], key: impl Fn(usize) -> K) -> Vec<Vec<usize>> {
+    let mut groups: HashMap<K, Vec<usize>> = HashMap::new();
+    for &index in indices {
+        groups.entry(key(index)).or_default().push(index);
+    }
+    groups.into_values().filter(|group| group.len() >= 2).collect()
+}
+
+// 成功计算了哈希的文件
+fn candidates_with_hash(indices: &[usize], hashes: &HashMap<usize, String>) -> Vec<usize> {
+    indices.iter().copied().filter(|index| hashes.contains_key(index)).collect()
+}
+
+// 用threads个线程对indices中的每个文件调用hash，返回成功的结果(文件下标 -> 哈希值)
+// 失败的文件记录在report中，被取消而中断的不记录
+fn hash_in_parallel(
+    files: &[SingleFileInformations],
+    indices: &[usize],
+    threads: usize,
+    cancel: &CancelToken,
+    report: &mut SearchReport,
+    hash: impl Fn(&SingleFileInformations) -> io::Result<String> + Sync,
+) -> HashMap<usize, String> {
+    let next = AtomicUsize::new(0);
+    let outcomes: Vec<(usize, io::Result<String>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.max(1).min(indices.len()))
+            .map(|_| scope.spawn(|| {
+                let mut outcomes = Vec::new();
+                while !cancel.is_cancelled() {
+                    let Some(&index) = indices.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                        break;
+                    };
+                    outcomes.push((index, hash(&files[index])));
+                }
+                outcomes
+            }))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    });
+
+    let mut hashes = HashMap::new();
+    for (index, outcome) in outcomes {
+        match outcome {
+            Ok(hash) => {
+                hashes.insert(index, hash);
+            },
+            Err(_) if cancel.is_cancelled() => {},
+            Err(e) => report.record(&files[index].path, SearchOperation::Hash, &e),
+        }
+    }
+    hashes
+}
+
+// 文件开头和结尾各PARTIAL_HASH_BYTES字节的哈希，较小的文件直接计算完整哈希
+fn partial_hash(path: &Path, size: u64, algorithm: HashAlgorithm, cancel: &CancelToken) -> io::Result<String> {
+    if size <= 2 * PARTIAL_HASH_BYTES {
+        return hash_file(path, algorithm, cancel);
+    }
+    let mut file = fs::File::open(path)?;
+    let mut hasher = FileHasher::new(algorithm);
+    let mut buffer = vec![0; PARTIAL_HASH_BYTES as usize];
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    file.seek(SeekFrom::Start(size - PARTIAL_HASH_BYTES))?;
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    Ok(hasher.finish())
+}
+
+/// 一组内容相同的文件
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64, // 每个文件的大小
+    pub files: Vec<SingleFileInformations>,
+    pub keep: Vec<bool>, // 与files一一对应，是否保留该文件
+}
+
+impl DuplicateGroup {
+    // 没有保留的文件，处理重复文件时只处理这些
+    pub fn duplicates(&self) -> impl Iterator<Item = &SingleFileInformations> {
+        self.files.iter().zip(&self.keep).filter(|(_, keep)| !**keep).map(|(file, _)| file)
+    }
+
+    // 处理掉没有保留的文件后可以释放的空间
+    pub fn reclaimable(&self) -> u64 {
+        self.size * self.duplicates().count() as u64
+    }
+
+    // 至少保留了一个文件，否则这一组不会被处理
+    pub fn has_keeper(&self) -> bool {
+        self.keep.iter().any(|keep| *keep)
+    }
+
+    // 按规则重新选择保留的文件，每组只保留一个；Manual不改变当前的选择
+    pub fn apply_policy(&mut self, policy: &KeeperPolicy) {
+        let keeper = match policy {
+            KeeperPolicy::Oldest => (0..self.files.len()).min_by_key(|&index| self.files[index].time),
+            KeeperPolicy::Newest => (0..self.files.len()).max_by_key(|&index| (self.files[index].time, std::cmp::Reverse(index))),
+            KeeperPolicy::ShortestPath => self.shortest_path(|_| true),
+            KeeperPolicy::PreferredFolder(folder) => self.shortest_path(|file| file.path.starts_with(folder))
+                .or_else(|| self.shortest_path(|_| true)),
+            KeeperPolicy::Manual => return,
+        };
+        for (index, keep) in self.keep.iter_mut().enumerate() {
+            *keep = Some(index) == keeper;
+        }
+    }
+
+    // 满足条件的文件中路径最短的一个(按路径层数，其次按长度)
+    fn shortest_path(&self, condition: impl Fn(&SingleFileInformations) -> bool) -> Option<usize> {
+        (0..self.files.len())
+            .filter(|&index| condition(&self.files[index]))
+            .min_by_key(|&index| {
+                let path = &self.files[index].path;
+                (path.components().count(), path.as_os_str().len())
+            })
+    }
+}
+
+/// 每组重复文件中保留哪一个
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeeperPolicy {
+    Oldest, // 修改时间最早的
+    Newest, // 修改时间最晚的
+    ShortestPath, // 路径最短的
+    PreferredFolder(PathBuf), // 位于指定文件夹中的(有多个时取路径最短的)，组中没有这样的文件时保留路径最短的
+    Manual, // 由用户逐个选择
+}
+
+// 把find_duplicates找到的各组下标转换为DuplicateGroup，默认保留每组的第一个文件
+pub fn into_groups(files: &[SingleFileInformations], groups: &[Vec<usize>]) -> Vec<DuplicateGroup> {
+    groups.iter()
+        .map(|group| {
+            let files: Vec<SingleFileInformations> = group.iter().map(|&index| files[index].clone()).collect();
+            let mut keep = vec![false; files.len()];
+            keep[0] = true;
+            DuplicateGroup {
+                hash: files[0].hash.clone(),
+                size: files[0].size,
+                files,
+                keep,
+            }
+        })
+        .collect()
+}
+
+/// 处理重复文件的结果
+#[derive(Debug, Default)]
+pub struct DuplicateActionReport {
+    pub processed: Vec<PathBuf>, // 已处理的文件
+    pub reclaimed: u64, // 释放的空间
+    pub skipped_groups: usize, // 没有保留任何文件而未处理的组
+    pub symlinks: usize, // 替换为链接时，跨文件系统而使用符号链接的文件数量(其余为硬链接)
+    pub errors: Vec<(PathBuf, String)>, // 处理失败的文件
+    pub cancelled: bool, // 是否被取消，取消时只处理了一部分文件
+}
+
+impl DuplicateActionReport {
+    fn record(&mut self, file: &SingleFileInformations, result: io::Result<()>) {
+        match result {
+            Ok(()) => {
+                self.processed.push(file.path.clone());
+                self.reclaimed += file.size;
+            },
+            Err(e) => self.errors.push((file.path.clone(), e.to_string())),
+        }
+    }
+}
+
+// 对每组中没有保留的文件执行action；没有保留任何文件的组整个跳过，以免所有副本都被处理掉
+// 处理前检查文件的大小是否仍与搜索时相同，已经改变的文件不处理
+// 每处理一个文件前检查cancel，取消后不再处理剩下的文件，report.cancelled为true
+fn for_each_duplicate(groups: &[DuplicateGroup], progress: &DuplicateActionProgress, cancel: &CancelToken, mut action: impl FnMut(&DuplicateGroup, &SingleFileInformations) -> io::Result<()>) -> DuplicateActionReport {
+    let mut report = DuplicateActionReport::default();
+    let total = groups.iter().filter(|group| group.has_keeper()).map(|group| group.duplicates().count()).sum();
+    progress.total.store(total, Ordering::Relaxed);
+    'groups: for group in groups {
+        if !group.has_keeper() {
+            report.skipped_groups += 1;
+            continue;
+        }
+        for file in group.duplicates() {
+            if cancel.is_cancelled() {
+                report.cancelled = true;
+                break 'groups;
+            }
+            let result = fs::symlink_metadata(&file.path).and_then(|metadata| {
+                if metadata.len() != group.size {
+                    return Err(io::Error::other("文件在搜索之后已被修改"));
+                }
+                action(group, file)
+            });
+            // 处理中途被取消(如比较内容时)的文件保持不变，不算作失败
+            if result.is_err() && cancel.is_cancelled() {
+                report.cancelled = true;
+                break 'groups;
+            }
+            report.record(file, result);
+            progress.done.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    report
+}
+
+// 把没有保留的文件移到回收站
+pub fn trash_duplicates(groups: &[DuplicateGroup], progress: &DuplicateActionProgress, cancel: &CancelToken) -> DuplicateActionReport {
+    for_each_duplicate(groups, progress, cancel, |_, file| trash::delete(&file.path).map_err(io::Error::other))
+}
+
+// 把没有保留的文件移动到destination中，同名文件加上序号，不覆盖已有的文件
+// 跨文件系统时先复制再删除原文件
+pub fn move_duplicates(groups: &[DuplicateGroup], destination: &Path, progress: &DuplicateActionProgress, cancel: &CancelToken) -> DuplicateActionReport {
+    for_each_duplicate(groups, progress, cancel, |_, file| {
+        let dest_path = unused_path(destination, &file.name);
+        match fs::rename(&file.path, &dest_path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                fs::copy(&file.path, &dest_path)?;
+                fs::remove_file(&file.path)
+            },
+            Err(e) => Err(e),
+        }
+    })
+}
+
+// 把没有保留的文件替换为指向该组第一个保留文件的链接，原来的位置上仍然可以访问到相同的内容
+// 同一文件系统中使用硬链接，跨文件系统时使用符号链接
+// 替换前逐字节比较两个文件的内容；链接先以临时文件名创建，再通过重命名原子地替换原文件，失败时原文件保持不变
+pub fn link_duplicates(groups: &[DuplicateGroup], progress: &DuplicateActionProgress, cancel: &CancelToken) -> DuplicateActionReport {
+    let mut symlinks = 0;
+    let mut report = for_each_duplicate(groups, progress, cancel, |group, file| {
+        let keeper = group.files.iter().zip(&group.keep).find(|(_, keep)| **keep).map(|(keeper, _)| keeper).unwrap();
+        if !same_contents(&keeper.path, &file.path, cancel)? {
+            return Err(io::Error::other("内容与保留的文件不同"));
+        }
+        let temporary = temporary_path(&file.path);
+        let symbolic = match fs::hard_link(&keeper.path, &temporary) {
+            Ok(()) => false,
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                symlink_file(&keeper.path, &temporary)?;
+                true
+            },
+            Err(e) => return Err(e),
+        };
+        if let Err(e) = fs::rename(&temporary, &file.path) {
+            let _ = fs::remove_file(&temporary);
+            return Err(e);
+        }
+        if symbolic {
+            symlinks += 1;
+        }
+        Ok(())
+    });
+    report.symlinks = symlinks;
+    report
+}
+
+// 逐字节比较两个文件的内容，大文件比较期间被取消时返回错误
+fn same_contents(a: &Path, b: &Path, cancel: &CancelToken) -> io::Result<bool> {
+    let mut a = fs::File::open(a)?;
+    let mut b = fs::File::open(b)?;
+    let mut buffer_a = vec![0; 64 * 1024];
+    let mut buffer_b = vec![0; 64 * 1024];
+    loop {
+        if cancel.is_cancelled() {
+            return Err(io::Error::other("已取消"));
+        }
+        let read_a = read_full(&mut a, &mut buffer_a)?;
+        let read_b = read_full(&mut b, &mut buffer_b)?;
+        if buffer_a[..read_a] != buffer_b[..read_b] {
+            return Ok(false);
+        }
+        if read_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+// 尽量读满buffer，只有到达文件末尾时才会读到的比buffer短
+fn read_full(file: &mut fs::File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// 与path在同一目录中、尚不存在的临时文件名，重命名到path时才是原子的
+fn temporary_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    (0..)
+        .map(|number| path.with_file_name(format!(".{}.{}.eslink", name, number)))
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .unwrap()
+}
+
+#[cfg(unix)]
+fn symlink_file(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_file(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+// destination中还不存在的文件名: name、name (1)、name (2)...，序号加在扩展名之前
+fn unused_path(destination: &Path, name: &str) -> PathBuf {
+    let path = destination.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.rfind('.') {
+        Some(index) if index > 0 => name.split_at(index),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|number| destination.join(format!("{} ({}){}", stem, number, extension)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 大于2*PARTIAL_HASH_BYTES，部分哈希只读取首尾，不是完整哈希
+    const LARGE_SIZE: u64 = 3 * PARTIAL_HASH_BYTES;
+
+    fn file_info(path: &Path) -> SingleFileInformations {
+        let metadata = fs::metadata(path).unwrap();
+        SingleFileInformations {
+            path: path.to_path_buf(),
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            size: metadata.len(),
+            time: 0,
+            hash: String::new(),
+            hash_algorithm: HashAlgorithm::default(),
+            link_target: None,
+            matches: Vec::new(),
+            created: None,
+            accessed: None,
+            is_dir: false,
+            file_count: 0,
+        }
+    }
+
+    // 只有最后一个字节不是0的文件
+    fn large_file(path: &Path, last_byte: u8) {
+        let mut contents = vec![0; LARGE_SIZE as usize];
+        contents[LARGE_SIZE as usize - 1] = last_byte;
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn large_files_with_different_tails_are_not_duplicates() {
+        let directory = tempfile::tempdir().unwrap();
+        let a = directory.path().join("a.bin");
+        let b = directory.path().join("b.bin");
+        large_file(&a, 1);
+        large_file(&b, 2);
+        let mut files = vec![file_info(&a), file_info(&b)];
+        assert_eq!(files[0].size, LARGE_SIZE);
+
+        let progress = DuplicateProgress::default();
+        let mut report = SearchReport::default();
+        let groups = find_duplicates(&mut files, HashAlgorithm::Xxh3, 2, &progress, &CancelToken::new(), &mut report);
+        assert!(report.errors.is_empty());
+        assert!(groups.is_empty());
+        // 大小相同的两个文件都比较了首尾，首尾不同，不需要读取完整内容
+        assert_eq!(progress.partial_hashed.load(Ordering::Relaxed), 2);
+        assert_eq!(progress.full_hashed.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn large_duplicates_can_be_moved() {
+        let directory = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let a = directory.path().join("a.bin");
+        let b = directory.path().join("b.bin");
+        large_file(&a, 1);
+        large_file(&b, 1);
+        let files = vec![file_info(&a), file_info(&b)];
+        let mut groups = into_groups(&files, &[vec![0, 1]]);
+        assert_eq!(groups[0].size, LARGE_SIZE);
+        assert_eq!(groups[0].reclaimable(), LARGE_SIZE);
+
+        let report = move_duplicates(&groups, destination.path(), &DuplicateActionProgress::default(), &CancelToken::new());
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.processed, vec![b.clone()]);
+        assert_eq!(report.reclaimed, LARGE_SIZE);
+        assert!(a.exists());
+        assert!(!b.exists());
+        assert_eq!(fs::metadata(destination.path().join("b.bin")).unwrap().len(), LARGE_SIZE);
+
+        // 搜索之后大小改变的文件不处理
+        let moved = destination.path().join("b.bin");
+        groups[0].files[1] = file_info(&moved);
+        fs::OpenOptions::new().write(true).open(&moved).unwrap().set_len(LARGE_SIZE + 1).unwrap();
+        let report = move_duplicates(&groups, directory.path(), &DuplicateActionProgress::default(), &CancelToken::new());
+        assert!(report.processed.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        assert!(moved.exists());
+    }
+
+    #[test]
+    fn duplicates_are_replaced_with_hard_links() {
+        let directory = tempfile::tempdir().unwrap();
+        let keeper = directory.path().join("keeper.txt");
+        let copy = directory.path().join("copy.txt");
+        fs::write(&keeper, b"same contents").unwrap();
+        fs::write(&copy, b"same contents").unwrap();
+        let groups = into_groups(&[file_info(&keeper), file_info(&copy)], &[vec![0, 1]]);
+
+        let progress = DuplicateActionProgress::default();
+        let report = link_duplicates(&groups, &progress, &CancelToken::new());
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(progress.total.load(Ordering::Relaxed), 1);
+        assert_eq!(progress.done.load(Ordering::Relaxed), 1);
+        assert_eq!(report.processed, vec![copy.clone()]);
+        assert_eq!(report.reclaimed, 13);
+        assert_eq!(report.symlinks, 0);
+        assert_eq!(fs::read(&copy).unwrap(), b"same contents");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(fs::metadata(&keeper).unwrap().ino(), fs::metadata(&copy).unwrap().ino());
+        }
+        // 临时文件已经通过重命名替换了原文件
+        let names: Vec<_> = fs::read_dir(directory.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(names.len(), 2);
+    }
+
+    #[test]
+    fn files_with_different_contents_are_not_linked() {
+        let directory = tempfile::tempdir().unwrap();
+        let keeper = directory.path().join("keeper.txt");
+        let copy = directory.path().join("copy.txt");
+        fs::write(&keeper, b"contents a").unwrap();
+        fs::write(&copy, b"contents b").unwrap();
+        // 例如搜索之后被修改、但大小没有变化的文件
+        let groups = into_groups(&[file_info(&keeper), file_info(&copy)], &[vec![0, 1]]);
+
+        let report = link_duplicates(&groups, &DuplicateActionProgress::default(), &CancelToken::new());
+        assert!(report.processed.is_empty());
+        assert_eq!(report.reclaimed, 0);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(fs::read(&copy).unwrap(), b"contents b");
+        let names: Vec<_> = fs::read_dir(directory.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(names.len(), 2);
+    }
+
+    #[test]
+    fn cancelled_link_leaves_files_unchanged() {
+        let directory = tempfile::tempdir().unwrap();
+        let keeper = directory.path().join("keeper.txt");
+        let copy = directory.path().join("copy.txt");
+        fs::write(&keeper, b"same contents").unwrap();
+        fs::write(&copy, b"same contents").unwrap();
+        let groups = into_groups(&[file_info(&keeper), file_info(&copy)], &[vec![0, 1]]);
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let progress = DuplicateActionProgress::default();
+        let report = link_duplicates(&groups, &progress, &cancel);
+        assert!(report.cancelled);
+        assert!(report.processed.is_empty());
+        assert!(report.errors.is_empty());
+        assert_eq!(progress.total.load(Ordering::Relaxed), 1);
+        assert_eq!(progress.done.load(Ordering::Relaxed), 0);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_ne!(fs::metadata(&keeper).unwrap().ino(), fs::metadata(&copy).unwrap().ino());
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// 空文件和空目录
+// 空文件是长度为0的普通文件；空目录是其中(包括各层子目录)没有任何文件的目录，
+// 可以选择把Thumbs.db、.DS_Store这类系统自动生成的文件视为不存在
+// 清理时从最深的条目开始逐个移到回收站，子目录移除后再处理父目录；每个条目在移除前都重新检查是否仍然为空
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::SingleFileInformations;
+
+// 默认视为空的系统文件
+pub const DEFAULT_JUNK_NAMES: [&str; 4] = ["Thumbs.db", "desktop.ini", ".DS_Store", "ehthumbs.db"];
+
+pub fn default_junk_names() -> Vec<String> {
+    DEFAULT_JUNK_NAMES.iter().map(|name| name.to_string()).collect()
+}
+
+// 文件名是否在junk列表中，不区分大小写
+pub fn is_junk(name: &OsStr, junk: &[String]) -> bool {
+    match name.to_str() {
+        Some(name) => junk.iter().any(|junk_name| junk_name.eq_ignore_ascii_case(name)),
+        None => false,
+    }
+}
+
+// 目录中(包括各层子目录)是否只有junk列表中的文件，不跟随符号链接
+pub fn is_empty_directory(path: &Path, junk: &[String]) -> io::Result<bool> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            if !is_empty_directory(&entry.path(), junk)? {
+                return Ok(false);
+            }
+        } else if !is_junk(&entry.file_name(), junk) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// 一次清理的结果
+#[derive(Debug, Default)]
+pub struct CleanupReport {
+    pub removed: Vec<PathBuf>, // 已移到回收站的条目
+    pub skipped: Vec<PathBuf>, // 搜索之后已经不再为空(或已不存在)的条目，没有移除
+    pub errors: Vec<(PathBuf, String)>, // 检查或移除失败的条目
+}
+
+// 把空文件和空目录移到回收站，junk为视为空的文件名(不视为空时传入空列表)
+pub fn remove_empty(files: &[SingleFileInformations], junk: &[String]) -> CleanupReport {
+    let mut report = CleanupReport::default();
+    let mut entries: Vec<&SingleFileInformations> = files.iter().collect();
+    // 按路径深度从深到浅处理，子目录先被移除，父目录随后才会被判断为空
+    entries.sort_by_key(|file| std::cmp::Reverse(file.path.components().count()));
+
+    for file in entries {
+        let still_empty = if file.is_dir {
+            is_empty_directory(&file.path, junk)
+        } else {
+            fs::symlink_metadata(&file.path).map(|metadata| metadata.is_file() && metadata.len() == 0)
+        };
+        match still_empty {
+            Ok(true) => match trash::delete(&file.path) {
+                Ok(()) => report.removed.push(file.path.clone()),
+                Err(e) => report.errors.push((file.path.clone(), e.to_string())),
+            },
+            Ok(false) => report.skipped.push(file.path.clone()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => report.skipped.push(file.path.clone()),
+            Err(e) => report.errors.push((file.path.clone(), e.to_string())),
+        }
+    }
+    report
+}
@@ -0,0 +1,69 @@
+// 按扩展名过滤文件
+// 扩展名统一使用小写、不带"."的形式，支持tar.gz这样的多段扩展名
+
+use std::collections::HashSet;
+use std::path::Path;
+
+// 把用户输入的扩展名列表(以";"、","或空白分隔，可以带"."或"*.")转换为统一的形式
+pub fn parse_extensions(text: &str) -> Vec<String> {
+    text.split([';', ',', ' ', '\n', '\t'])
+        .map(normalize_extension)
+        .filter(|extension| !extension.is_empty())
+        .collect()
+}
+
+pub fn normalize_extension(extension: &str) -> String {
+    extension.trim()
+        .trim_start_matches('*')
+        .trim_start_matches('.')
+        .to_lowercase()
+}
+
+// 文件名所有可能的扩展名，例如 a.tar.gz -> ["tar.gz", "gz"]
+// 以"."开头的隐藏文件名(如.bashrc)本身不算扩展名
+pub fn extension_candidates(path: &Path) -> Vec<String> {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Vec::new();
+    };
+    let name = name.to_lowercase();
+    let name = name.strip_prefix('.').unwrap_or(&name);
+    name.match_indices('.')
+        .map(|(index, _)| name[index + 1..].to_string())
+        .filter(|extension| !extension.is_empty())
+        .collect()
+}
+
+/// 扩展名白名单/黑名单
+/// 白名单为空时不限制；黑名单优先于白名单
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionFilter {
+    allowed: Option<HashSet<String>>,
+    excluded: HashSet<String>,
+}
+
+impl ExtensionFilter {
+    pub fn new(allowed: &[String], excluded: &[String]) -> Self {
+        Self {
+            allowed: if allowed.is_empty() {
+                None
+            } else {
+                Some(allowed.iter().map(|extension| normalize_extension(extension)).collect())
+            },
+            excluded: excluded.iter().map(|extension| normalize_extension(extension)).collect(),
+        }
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.allowed.is_none() && self.excluded.is_empty() {
+            return true;
+        }
+        let candidates = extension_candidates(path);
+        if candidates.iter().any(|extension| self.excluded.contains(extension)) {
+            return false;
+        }
+        match &self.allowed {
+            Some(allowed) => candidates.iter().any(|extension| allowed.contains(extension)),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// 通配符(glob)过滤
+// 模式匹配的是相对于搜索根目录的路径，例如 **/*.log、src/**/test_*.rs
+// 与.gitignore类似，不含"/"的模式(如 *.log)匹配任意层级中的文件名
+
+use std::path::Path;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+
+// 把一个模式编译为Glob，*和?不跨越路径分隔符，只有**可以匹配多层目录
+fn compile_glob(pattern: &str) -> Result<Glob, globset::Error> {
+    let pattern = pattern.trim();
+    let pattern = if pattern.contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    GlobBuilder::new(&pattern).literal_separator(true).build()
+}
+
+// 检查一组模式是否都是合法的通配符
+pub fn validate_globs(patterns: &[String]) -> Result<(), globset::Error> {
+    for pattern in patterns {
+        compile_glob(pattern)?;
+    }
+    Ok(())
+}
+
+// 把以";"或换行分隔的文本拆分为模式列表(不用","分隔，因为{a,b}中会用到)
+pub fn split_patterns(text: &str) -> Vec<String> {
+    text.split([';', '\n'])
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .map(|pattern| pattern.to_string())
+        .collect()
+}
+
+// 编译一组模式，不合法的模式会被忽略
+pub fn build_glob_set(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match compile_glob(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            },
+            Err(e) => eprintln!("忽略无效的通配符 {}: {}", pattern, e),
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// 一次搜索使用的排除通配符，用于在遍历时跳过整个目录
+/// 文件是否匹配包含/排除通配符由过滤条件树中的Predicate::Glob判断
+#[derive(Debug, Clone)]
+pub struct PathGlobs {
+    exclude: GlobSet,
+}
+
+impl PathGlobs {
+    pub fn new(exclude: &[String]) -> Self {
+        Self {
+            exclude: build_glob_set(exclude),
+        }
+    }
+
+    // 目录本身匹配排除模式(如 **/node_modules)时整个目录都不再搜索
+    pub fn excludes_directory(&self, relative: &Path) -> bool {
+        !relative.as_os_str().is_empty() && self.exclude.is_match(relative)
+    }
+}
@@ -0,0 +1,274 @@
+// 哈希值缓存
+// 记录已经计算过的文件哈希值，保存在用户数据目录中(例如Linux下的~/.local/share/ExtendedSearch/hash_cache.json)
+// 以规范化后的路径为键，文件大小、修改时间和inode(Unix下)都没有变化时直接使用缓存的哈希值，不再读取文件
+// 同一个文件可以分别缓存各种算法的哈希值
+// 搜索时由所有工作线程共享，搜索结束后有新的条目时才写回文件
+// 同时打开的多个缓存(例如搜索过程中清理缓存)各自只记录自己的修改，保存时合并到磁盘上最新的内容中，不会互相覆盖
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+
+use super::settings::HashAlgorithm;
+
+// 缓存文件在数据目录中的位置
+const CACHE_DIRECTORY: &str = "ExtendedSearch";
+const CACHE_FILE: &str = "hash_cache.json";
+
+// 同一进程中的保存依次进行，读取磁盘上的缓存和重命名之间不会插入其他保存
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 一个文件的哈希值，以及计算时文件的状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedHash {
+    size: u64,
+    modified: u64, // 修改时间，自1970年以来的纳秒数
+    #[serde(default)]
+    inode: Option<u64>,
+    hashes: HashMap<HashAlgorithm, String>,
+}
+
+impl CachedHash {
+    // 文件当前的状态，无法确定修改时间的文件不缓存
+    fn stamp(metadata: &fs::Metadata) -> Option<(u64, u64, Option<u64>)> {
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let modified = u64::try_from(modified.as_nanos()).ok()?;
+        Some((metadata.len(), modified, inode(metadata)))
+    }
+
+    fn matches(&self, metadata: &fs::Metadata) -> bool {
+        Self::stamp(metadata) == Some((self.size, self.modified, self.inode))
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt as _;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+// 加载之后的修改，保存时重新应用到磁盘上的缓存
+#[derive(Debug, Default)]
+struct Changes {
+    cleared: bool, // 清空了全部条目
+    updated: HashSet<String>, // 新增或修改的条目
+    removed: HashSet<String>, // 删除的条目
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        !self.cleared && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// 保存在磁盘上的哈希值缓存
+#[derive(Debug, Default)]
+pub struct HashCache {
+    location: Option<PathBuf>, // 缓存文件，无法确定数据目录时为None，此时只在内存中缓存
+    entries: Mutex<HashMap<String, CachedHash>>, // 规范化路径 -> 哈希值
+    changes: Mutex<Changes>, // 加载之后的修改，总是在entries之后加锁
+}
+
+impl HashCache {
+    // 缓存文件的默认位置
+    pub fn default_location() -> Option<PathBuf> {
+        dirs_next::data_dir().map(|directory| directory.join(CACHE_DIRECTORY).join(CACHE_FILE))
+    }
+
+    // 从默认位置加载缓存
+    pub fn open() -> Self {
+        match Self::default_location() {
+            Some(location) => Self::load(location),
+            None => Self::default(),
+        }
+    }
+
+    // 从指定的文件加载缓存，文件不存在或已损坏时从空缓存开始
+    pub fn load(location: PathBuf) -> Self {
+        Self {
+            entries: Mutex::new(read_entries(&location)),
+            location: Some(location),
+            changes: Mutex::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    // 缓存键: 规范化后的路径，无法规范化或不是UTF-8的路径不缓存
+    fn key(path: &Path) -> Option<String> {
+        fs::canonicalize(path).ok()?.into_os_string().into_string().ok()
+    }
+
+    // 文件没有变化时返回缓存的该算法的哈希值
+    pub fn get(&self, path: &Path, metadata: &fs::Metadata, algorithm: HashAlgorithm) -> Option<String> {
+        let key = Self::key(path)?;
+        let entries = self.entries.lock().unwrap();
+        entries.get(&key)
+            .filter(|cached| cached.matches(metadata))
+            .and_then(|cached| cached.hashes.get(&algorithm).cloned())
+    }
+
+    // 记录文件的哈希值，metadata应为计算哈希之前读取的元数据
+    // 文件已经改变时丢弃之前用其他算法计算的哈希值
+    pub fn insert(&self, path: &Path, metadata: &fs::Metadata, algorithm: HashAlgorithm, hash: &str) {
+        let (Some(key), Some((size, modified, inode))) = (Self::key(path), CachedHash::stamp(metadata)) else {
+            return;
+        };
+        let mut entries = self.entries.lock().unwrap();
+        let cached = entries.entry(key.clone()).or_insert_with(|| CachedHash { size, modified, inode, hashes: HashMap::new() });
+        if !cached.matches(metadata) {
+            *cached = CachedHash { size, modified, inode, hashes: HashMap::new() };
+        }
+        cached.hashes.insert(algorithm, hash.to_string());
+        let mut changes = self.changes.lock().unwrap();
+        changes.removed.remove(&key);
+        changes.updated.insert(key);
+    }
+
+    // 删除文件已经不存在或已经改变的条目，返回删除的数量
+    pub fn prune(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let stale: Vec<String> = entries.iter()
+            .filter(|(path, cached)| !fs::metadata(path).is_ok_and(|metadata| cached.matches(&metadata)))
+            .map(|(path, _)| path.clone())
+            .collect();
+        let mut changes = self.changes.lock().unwrap();
+        for path in &stale {
+            entries.remove(path);
+            changes.updated.remove(path);
+            changes.removed.insert(path.clone());
+        }
+        stale.len()
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clear();
+        *self.changes.lock().unwrap() = Changes { cleared: true, ..Changes::default() };
+    }
+
+    // 有修改时写回缓存文件
+    // 重新读取磁盘上的缓存并应用本次的修改，加载之后其他缓存保存的条目不会丢失；
+    // 先写入临时文件再重命名，写到一半失败不会破坏原来的缓存
+    pub fn save(&self) -> io::Result<()> {
+        let Some(location) = &self.location else {
+            return Ok(());
+        };
+        let _guard = SAVE_LOCK.lock().unwrap();
+        let entries = self.entries.lock().unwrap();
+        let mut changes = self.changes.lock().unwrap();
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let mut merged = if changes.cleared { HashMap::new() } else { read_entries(location) };
+        for path in &changes.removed {
+            merged.remove(path);
+        }
+        for path in &changes.updated {
+            if let Some(cached) = entries.get(path) {
+                merged.insert(path.clone(), cached.clone());
+            }
+        }
+
+        if let Some(directory) = location.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let text = serde_json::to_string(&merged)?;
+        // 临时文件名带上进程号，同时保存的多个进程不会写入同一个临时文件
+        let temporary = location.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&temporary, text)?;
+        if let Err(e) = fs::rename(&temporary, location) {
+            let _ = fs::remove_file(&temporary);
+            return Err(e);
+        }
+        *changes = Changes::default();
+        Ok(())
+    }
+}
+
+// 读取缓存文件，文件不存在或已损坏时返回空缓存
+fn read_entries(location: &Path) -> HashMap<String, CachedHash> {
+    fs::read_to_string(location)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 创建文件并记录其哈希值(这里不需要真正的哈希值)
+    fn insert(cache: &HashCache, path: &Path, hash: &str) {
+        fs::write(path, hash).unwrap();
+        cache.insert(path, &fs::metadata(path).unwrap(), HashAlgorithm::Sha256, hash);
+    }
+
+    fn cached(location: &Path, path: &Path) -> Option<String> {
+        HashCache::load(location.to_path_buf()).get(path, &fs::metadata(path).ok()?, HashAlgorithm::Sha256)
+    }
+
+    #[test]
+    fn concurrent_caches_merge_on_save() {
+        let directory = tempfile::tempdir().unwrap();
+        let location = directory.path().join("cache").join(CACHE_FILE);
+        let (a, b) = (directory.path().join("a"), directory.path().join("b"));
+
+        let first = HashCache::load(location.clone());
+        let second = HashCache::load(location.clone());
+        insert(&first, &a, "1");
+        insert(&second, &b, "2");
+        first.save().unwrap();
+        second.save().unwrap();
+
+        assert_eq!(cached(&location, &a).as_deref(), Some("1"));
+        assert_eq!(cached(&location, &b).as_deref(), Some("2"));
+        // 只留下缓存文件，没有残留的临时文件
+        assert_eq!(fs::read_dir(location.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn prune_and_clear_survive_concurrent_inserts() {
+        let directory = tempfile::tempdir().unwrap();
+        let location = directory.path().join(CACHE_FILE);
+        let (a, b, c) = (directory.path().join("a"), directory.path().join("b"), directory.path().join("c"));
+
+        let cache = HashCache::load(location.clone());
+        insert(&cache, &a, "1");
+        cache.save().unwrap();
+        let key = HashCache::key(&a).unwrap();
+        fs::remove_file(&a).unwrap();
+
+        // 清理时另一个缓存正在记录新的哈希值
+        let search = HashCache::load(location.clone());
+        let maintenance = HashCache::load(location.clone());
+        insert(&search, &b, "2");
+        assert_eq!(maintenance.prune(), 1);
+        maintenance.save().unwrap();
+        search.save().unwrap();
+        let entries = read_entries(&location);
+        assert!(!entries.contains_key(&key));
+        assert_eq!(cached(&location, &b).as_deref(), Some("2"));
+
+        // 清空之后保存的新条目保留，清空之前的条目不会被写回
+        let search = HashCache::load(location.clone());
+        let maintenance = HashCache::load(location.clone());
+        maintenance.clear();
+        maintenance.save().unwrap();
+        insert(&search, &c, "3");
+        search.save().unwrap();
+        assert_eq!(cached(&location, &b), None);
+        assert_eq!(cached(&location, &c).as_deref(), Some("3"));
+    }
+}
@@ -0,0 +1,73 @@
+// .gitignore / .ignore / .esignore 支持
+// 与ripgrep类似，每一层目录中的忽略文件只作用于该目录及其子目录，深层目录的规则优先
+// 同一层中后读取的文件优先: .esignore > .ignore > .gitignore
+
+use std::path::Path;
+use std::sync::Arc;
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+// 每一层目录中读取的忽略文件，按优先级从低到高排列
+pub const IGNORE_FILE_NAMES: [&str; 3] = [".gitignore", ".ignore", ".esignore"];
+
+/// 从搜索根目录到当前目录，每一层目录的忽略规则
+/// 子目录通过Arc共享父目录的规则，因此进入子目录时不需要复制
+#[derive(Debug, Default)]
+pub struct IgnoreStack {
+    parent: Option<Arc<IgnoreStack>>,
+    rules: Option<Gitignore>,
+}
+
+impl IgnoreStack {
+    // 搜索根目录之上没有任何规则
+    pub fn root() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    // 进入一个目录，读取其中的忽略文件
+    // 目录中没有忽略文件时直接沿用父目录的规则
+    pub fn enter(self: &Arc<Self>, directory: &Path) -> Arc<Self> {
+        let mut builder = GitignoreBuilder::new(directory);
+        let mut found = false;
+        for name in IGNORE_FILE_NAMES {
+            let ignore_file = directory.join(name);
+            if !ignore_file.is_file() {
+                continue;
+            }
+            found = true;
+            if let Some(e) = builder.add(&ignore_file) {
+                eprintln!("读取忽略文件 {} 时出错: {}", ignore_file.display(), e);
+            }
+        }
+        if !found {
+            return self.clone();
+        }
+
+        match builder.build() {
+            Ok(rules) => Arc::new(Self {
+                parent: Some(self.clone()),
+                rules: Some(rules),
+            }),
+            Err(e) => {
+                eprintln!("解析 {} 中的忽略文件时出错: {}", directory.display(), e);
+                self.clone()
+            }
+        }
+    }
+
+    // 路径是否被忽略，从最深的一层开始查找，第一条匹配的规则(包括!开头的反向规则)决定结果
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut level = Some(self);
+        while let Some(stack) = level {
+            if let Some(rules) = &stack.rules {
+                match rules.matched(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {},
+                }
+            }
+            level = stack.parent.as_deref();
+        }
+        false
+    }
+}
@@ -1,228 +1,656 @@
-use std::arch::global_asm;
-use std::os::windows::fs::MetadataExt;
 use std::string::String;
 use std::vec::Vec;
 use std::time::SystemTime as Time;
-use std::hash::Hash;
-use std::hash::Hasher;
-use std::collections::HashMap;
 use std::collections::HashSet;
-use std::io::{self, Read, Write};
+use std::io::{self, Read};
 use std::path::PathBuf;
 use std::path::Path;
 use std::fs;
 use std::thread;
-use std::fs::DirEntry;
-use sha2::{Sha256, Digest};
-use regex::Regex;
-use std::time::Duration;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::panic::{self, AssertUnwindSafe};
 use std::time::UNIX_EPOCH;
 use serde::Serialize;
 use serde::Deserialize;
-use trash;
 
 
 pub mod settings;
 pub mod build_tree;
+pub mod attributes;
+pub mod content;
+pub mod glob;
+pub mod ignore_files;
+pub mod file_types;
+pub mod predicate;
+pub mod rules;
+pub mod directories;
+pub mod empty;
+pub mod duplicates;
+pub mod hash_cache;
+pub mod checksum;
 
-#[derive(Serialize, Deserialize)]
+use settings::{HashAlgorithm, SearchOptions, SymlinkMode, TimeField};
+use directories::TotalsCache;
+use hash_cache::HashCache;
+use content::ContentMatch;
+use glob::PathGlobs;
+use ignore_files::IgnoreStack;
+use file_types::ExtensionFilter;
+use predicate::{Entry, Predicate};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SingleFileInformations {
     pub path: PathBuf,
     pub name: String,
     pub size: u64,
-    pub time: u64,
-    pub hash: String
+    pub time: u64, // 修改时间
+    pub hash: String,
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm, // hash使用的算法
+    #[serde(default)]
+    pub link_target: Option<PathBuf>, // 若该条目是符号链接，记录链接指向的路径
+    #[serde(default)]
+    pub matches: Vec<ContentMatch>, // 搜索文件内容时匹配的行
+    #[serde(default)]
+    pub created: Option<u64>, // 创建时间，文件系统不支持时为None
+    #[serde(default)]
+    pub accessed: Option<u64>, // 最后访问时间
+    #[serde(default)]
+    pub is_dir: bool, // 该条目是目录，此时size是其中所有文件的总大小
+    #[serde(default)]
+    pub file_count: u64, // 目录中(包括各层子目录中)的文件数量
+}
+
+/// 搜索过程中失败的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchOperation {
+    ReadDirectory, // 读取目录
+    ReadEntry, // 读取目录项
+    Metadata, // 读取文件元数据
+    ModifiedTime, // 读取修改时间
+    Hash, // 读取文件内容计算哈希
+    ReadLink, // 读取符号链接的目标
+    ReadContent, // 读取文件内容进行搜索
+    SearchDirectory, // 搜索目录时发生了内部错误(工作线程panic)
+}
+
+impl std::fmt::Display for SearchOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            SearchOperation::ReadDirectory => "读取目录",
+            SearchOperation::ReadEntry => "读取目录项",
+            SearchOperation::Metadata => "读取文件信息",
+            SearchOperation::ModifiedTime => "读取修改时间",
+            SearchOperation::Hash => "计算哈希值",
+            SearchOperation::ReadLink => "读取链接目标",
+            SearchOperation::ReadContent => "搜索文件内容",
+            SearchOperation::SearchDirectory => "搜索目录",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// 一条无法读取的路径
+#[derive(Debug, Clone)]
+pub struct SearchError {
+    pub path: PathBuf,
+    pub operation: SearchOperation,
+    pub kind: io::ErrorKind,
+}
+
+impl std::fmt::Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}失败: {})", self.path.display(), self.operation, self.kind)
+    }
+}
+
+/// 一次搜索的报告，记录遍历过程中被跳过的路径
+#[derive(Debug, Default)]
+pub struct SearchReport {
+    pub errors: Vec<SearchError>,
+    pub cancelled: bool, // 搜索是否被中途取消(此时结果不完整)
+}
+
+impl SearchReport {
+    // 记录一条错误，遍历会跳过出错的条目继续进行
+    fn record(&mut self, path: &Path, operation: SearchOperation, error: &io::Error) {
+        self.errors.push(SearchError {
+            path: path.to_path_buf(),
+            operation,
+            kind: error.kind(),
+        });
+    }
+
+    fn merge(&mut self, other: SearchReport) {
+        self.errors.extend(other.errors);
+    }
+}
+
+/// 搜索的取消标记
+/// 可以克隆后交给其他线程，调用cancel()后正在进行的搜索会尽快停止，已找到的结果仍然保留
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 搜索进度，由工作线程更新，可在其他线程(如UI线程)中随时读取
+#[derive(Debug, Default)]
+pub struct SearchProgress {
+    pub directories_scanned: AtomicUsize,
+    pub files_matched: AtomicUsize,
+}
+
+
+// 目录的唯一标识，跟随符号链接时用来检测循环和重复搜索
+// Unix下使用设备号+inode；其他平台没有稳定的接口，退而使用规范化后的路径
+#[cfg(unix)]
+type DirectoryKey = (u64, u64);
+#[cfg(not(unix))]
+type DirectoryKey = PathBuf;
+
+#[cfg(unix)]
+fn directory_key(_path: &Path, metadata: &fs::Metadata) -> Option<DirectoryKey> {
+    use std::os::unix::fs::MetadataExt as _;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn directory_key(path: &Path, _metadata: &fs::Metadata) -> Option<DirectoryKey> {
+    fs::canonicalize(path).ok()
+}
+
+// 记录一个即将搜索的目录，已经搜索过(或已在队列中)时返回false
+// 无法确定标识的目录总是允许搜索
+fn mark_visited(visited: &Mutex<HashSet<DirectoryKey>>, path: &Path, metadata: &fs::Metadata) -> bool {
+    match directory_key(path, metadata) {
+        Some(key) => visited.lock().unwrap().insert(key),
+        None => true,
+    }
+}
+
+/// 一个搜索根目录及其搜索深度范围
+/// 根目录中的直接子项深度为1，子目录中的项为2，依此类推
+/// 深度小于min_depth的文件和目录不作为结果，但仍会向下搜索；深度为max_depth的目录不再向下搜索
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchRoot {
+    pub path: PathBuf,
+    pub min_depth: usize,
+    pub max_depth: usize,
+}
+
+impl SearchRoot {
+    // 不限制深度
+    #[allow(dead_code)] // 界面总是指定深度范围
+    pub fn new(path: PathBuf) -> Self {
+        Self::with_depth(path, 0, usize::MAX)
+    }
+
+    pub fn with_depth(path: PathBuf, min_depth: usize, max_depth: usize) -> Self {
+        Self { path, min_depth, max_depth }
+    }
+
+    // 该深度的项是否可以作为结果
+    fn reports(&self, depth: usize) -> bool {
+        self.min_depth <= depth && depth <= self.max_depth
+    }
+}
+
+// 一次搜索中所有工作线程共享的参数和状态
+struct SearchContext<'a> {
+    predicate: &'a Predicate, // 每个文件都要满足的过滤条件树
+    roots: &'a [SearchRoot],
+    options: &'a SearchOptions, // 控制遍历: 剪枝、符号链接、深度、结果种类和哈希值，不参与对条目的求值
+    globs: PathGlobs, // 排除通配符，匹配的目录整个跳过
+    extensions: ExtensionFilter, // 扩展名白名单/黑名单，在读取元数据之前提前排除文件
+    visited: Mutex<HashSet<DirectoryKey>>, // 跟随符号链接时已搜索过的目录
+    totals: TotalsCache, // 作为结果的目录中文件的汇总
+    hash_cache: Option<HashCache>, // 记录哈希值时使用的磁盘缓存
+    cancel: &'a CancelToken,
+}
+
+// 一个待搜索的目录
+struct DirectoryJob {
+    path: PathBuf,
+    depth: usize, // 该目录相对于根目录的深度，根目录为0
+    root: usize, // 所属搜索根目录在roots中的下标
+    ignore: Option<Arc<IgnoreStack>>, // 从根目录到父目录的忽略规则，不遵循忽略文件时为None
 }
 
+// 目录遍历的工作队列，由所有工作线程共享
+struct WorkQueue {
+    pending: Vec<DirectoryJob>, // 待搜索的目录
+    active: usize, // 正在处理目录的线程数量
+}
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-         .file_name()
-         .to_str()
-         .map(|s| {s.starts_with(".")}) 
-         .unwrap_or(false)
+// 从队列中取出下一个待搜索的目录
+// 队列为空且没有线程正在处理目录时说明遍历已经结束，返回None；搜索被取消时也直接返回None
+fn next_directory(queue: &Mutex<WorkQueue>, wakeup: &Condvar, cancel: &CancelToken) -> Option<DirectoryJob> {
+    let mut state = queue.lock().unwrap();
+    loop {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        if let Some(job) = state.pending.pop() {
+            state.active += 1;
+            return Some(job);
+        }
+        if state.active == 0 {
+            return None;
+        }
+        state = wakeup.wait(state).unwrap();
+    }
+}
+
+// 用固定数量的工作线程遍历所有目录
+// 每个线程从共享队列中取目录，搜索其中的文件，并把发现的子目录放回队列
+// 每个目录中找到的文件通过on_batch立即交给调用者
+fn run_worker_pool(predicate: &Predicate, roots: &[SearchRoot], options: &SearchOptions, progress: &SearchProgress, cancel: &CancelToken, on_batch: &(dyn Fn(Vec<SingleFileInformations>) + Sync)) -> SearchReport {
+    let queue = Mutex::new(WorkQueue {
+        pending: roots.iter().enumerate().rev()
+            .map(|(index, root)| DirectoryJob {
+                path: root.path.clone(),
+                depth: 0,
+                root: index,
+                ignore: options.respect_ignore_files.then(IgnoreStack::root),
+            })
+            .collect(),
+        active: 0,
+    });
+    let wakeup = Condvar::new();
+    let thread_count = options.worker_threads.max(1);
+
+    let context = SearchContext {
+        predicate,
+        roots,
+        options,
+        globs: PathGlobs::new(&options.exclude_globs),
+        extensions: ExtensionFilter::new(&options.include_extensions, &options.exclude_extensions),
+        visited: Mutex::new(HashSet::new()),
+        totals: TotalsCache::new(if options.junk_as_empty { options.junk_names.clone() } else { Vec::new() }),
+        hash_cache: options.save_hash.then(HashCache::open),
+        cancel,
+    };
+
+    // 跟随符号链接时，所有已搜索的目录都记录在visited中，指回祖先目录的链接和重复链接的目录树只会被搜索一次
+    if options.symlink_mode == SymlinkMode::Follow {
+        for root in roots {
+            if let Ok(metadata) = fs::metadata(&root.path) {
+                mark_visited(&context.visited, &root.path, &metadata);
+            }
+        }
+    }
+
+    let report = thread::scope(|scope| {
+        let handles: Vec<_> = (0..thread_count)
+            .map(|_| scope.spawn(|| {
+                let mut report = SearchReport::default();
+                while let Some(job) = next_directory(&queue, &wakeup, cancel) {
+                    let mut subdirectories = Vec::new();
+                    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                        search_in_path(&context, &job, &mut subdirectories, &mut report)
+                    }));
+
+                    // 无论成功与否都要归还任务，否则其他线程会一直等待
+                    {
+                        let mut state = queue.lock().unwrap();
+                        state.pending.extend(subdirectories.into_iter().rev());
+                        state.active -= 1;
+                    }
+                    wakeup.notify_all();
+
+                    match outcome {
+                        Ok(files) => {
+                            progress.directories_scanned.fetch_add(1, Ordering::Relaxed);
+                            if !files.is_empty() {
+                                progress.files_matched.fetch_add(files.len(), Ordering::Relaxed);
+                                on_batch(files);
+                            }
+                        },
+                        // 某个目录的处理出现了panic时只跳过这个目录，记录在报告中，不让整个搜索线程崩溃
+                        Err(_) => report.record(&job.path, SearchOperation::SearchDirectory, &io::Error::other("internal error")),
+                    }
+                }
+                report
+            }))
+            .collect();
+
+        let mut all_reports = SearchReport::default();
+        for handle in handles {
+            all_reports.merge(handle.join().unwrap());
+        }
+        all_reports
+    });
+
+    // 把这次搜索新计算的哈希值写回缓存
+    if let Some(cache) = &context.hash_cache
+        && let Err(e) = cache.save() {
+        eprintln!("无法保存哈希值缓存: {}", e);
+    }
+    report
 }
 
 // search的内部实现，无需手动调用
-fn search_in_path( filter : fn( metadata:&fs::Metadata )->bool ,curpath: &PathBuf ,search_depth:u8 , file_regex: &String) -> Vec<SingleFileInformations> {
+// 只搜索curpath这一层，需要继续向下搜索的子目录放入subdirectories，由工作线程池调度
+// 无法读取的目录和文件会被跳过并记录到report中
+// 搜索被取消时立即返回已经找到的文件
+// 符号链接按options.symlink_mode处理，跟随时通过visited避免循环
+fn search_in_path(context: &SearchContext, job: &DirectoryJob, subdirectories: &mut Vec<DirectoryJob>, report: &mut SearchReport) -> Vec<SingleFileInformations> {
+    let SearchContext { predicate, options, globs, extensions, visited, cancel, .. } = context;
+    let curpath = &job.path;
+    let search_root = &context.roots[job.root];
+    let root = &search_root.path;
+    // 本层目录中各项的深度
+    let depth = job.depth.saturating_add(1);
     let mut result = Vec::new();
-    let mut handles = Vec::new();
-    for entry in fs::read_dir(curpath).unwrap() {
-        let entry = entry.unwrap();
+    let entries = match fs::read_dir(curpath) {
+        Ok(entries) => entries,
+        Err(e) => {
+            report.record(curpath, SearchOperation::ReadDirectory, &e);
+            return result;
+        }
+    };
+
+    // 加上本层目录中的.gitignore/.ignore/.esignore
+    let ignore = job.ignore.as_ref().map(|parent| parent.enter(curpath));
+
+    for entry in entries {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.record(curpath, SearchOperation::ReadEntry, &e);
+                continue;
+            }
+        };
         let path = entry.path();
-        // println!("Searching: {:?}", entry.file_name());
+
+        // 目录项本身的类型，不跟随符号链接
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                report.record(&path, SearchOperation::Metadata, &e);
+                continue;
+            }
+        };
+        // 扩展名过滤在读取元数据之前进行，普通文件不需要额外的系统调用
+        if !file_type.is_dir() && !file_type.is_symlink() && !extensions.matches(&path) {
+            continue;
+        }
+        let link_target = if file_type.is_symlink() {
+            if options.symlink_mode == SymlinkMode::Skip {
+                continue;
+            }
+            match fs::read_link(&path) {
+                Ok(target) => Some(target),
+                Err(e) => {
+                    report.record(&path, SearchOperation::ReadLink, &e);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+        // 只有跟随模式下才读取链接指向的目标，否则读取链接本身
+        let follow = link_target.is_none() || options.symlink_mode == SymlinkMode::Follow;
+        let metadata = match if follow { fs::metadata(&path) } else { fs::symlink_metadata(&path) } {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                report.record(&path, SearchOperation::Metadata, &e);
+                continue;
+            }
+        };
+
+        // 被忽略文件排除的文件和目录
+        if let Some(ignore) = &ignore
+            && ignore.is_ignored(&path, metadata.is_dir()) {
+            continue;
+        }
 
         // If it's a directory, recurse into it
-        if path.is_dir() {
+        if metadata.is_dir() {
 
-            // Skip directories if we've reached the max depth
-            if search_depth == 1 {
+            // Skip hidden directories
+            if !options.search_hidden_folders
+                && attributes::is_hidden(&path, &metadata) {
                 continue; 
             }
 
-            // Skip hidden directories
-            if unsafe{!settings::SEARCH_HIDDEN_FOLDERS} {
-                if is_hidden(&entry) {
-                    continue; 
+            // 排除列表中的目录和匹配排除通配符的目录整个跳过
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if is_excluded_directory(&path, relative, &options.excluded_directories) || globs.excludes_directory(relative) {
+                continue;
+            }
+
+            // 已经搜索过的目录(符号链接形成的循环或重复的目录树)不再搜索，也不再作为结果列出
+            if options.symlink_mode == SymlinkMode::Follow && !mark_visited(visited, &path, &metadata) {
+                continue;
+            }
+
+            // 目录本身作为结果，目录没有可以读取的内容
+            if options.result_kind.includes_directories() && search_root.reports(depth) {
+                let mut candidate = Entry::new(&path, relative, &metadata, false, 0, cancel).with_totals(&context.totals);
+                let matched = predicate.matches(&mut candidate);
+                // 结果需要带上目录中文件的汇总
+                let totals = if matched { candidate.directory_totals() } else { None };
+                if cancel.is_cancelled() {
+                    break;
+                }
+                if let Some(e) = candidate.error.take() {
+                    report.record(&path, SearchOperation::ReadDirectory, &e);
+                } else if let Some(totals) = totals {
+                    result.push(SingleFileInformations {
+                        name: entry.file_name().to_string_lossy().to_string(),
+                        size: totals.size,
+                        time: timestamp_seconds(&metadata, TimeField::Modified).unwrap_or(0),
+                        created: timestamp_seconds(&metadata, TimeField::Created).ok(),
+                        accessed: timestamp_seconds(&metadata, TimeField::Accessed).ok(),
+                        hash: String::new(),
+                        hash_algorithm: options.hash_algorithm,
+                        link_target: link_target.clone(),
+                        matches: Vec::new(),
+                        is_dir: true,
+                        file_count: totals.files,
+                        path: path.clone(),
+                    });
                 }
             }
 
-            // use multithreading to search in subdirectories
-            let file_regex_clone = file_regex.clone();
-            let handle = thread::spawn(move || {
-                search_in_path(filter, &path, search_depth - 1, &file_regex_clone)
-            });
-            handles.push(handle);
+            // Skip directories if we've reached the max depth
+            if depth >= search_root.max_depth {
+                continue; 
+            }
+
+            // 交给工作线程池继续搜索
+            subdirectories.push(DirectoryJob { path, depth, root: job.root, ignore: ignore.clone() });
 
         } else {
-            let metadata = fs::metadata(&path).unwrap();
-            if metadata.is_file() {
-
-                // 正则表达式匹配文件名
-                {
-                    let file_regex = file_regex.clone();
-                    if !file_regex.is_empty() {
-                        let regex = Regex::new(&file_regex).unwrap();
-                        if unsafe{settings::REGEX_CONTAIN_PATH} {
-                            if let Some(file_name) = path.to_str() {
-                                println!("Full path: {}", file_name);
-                                if !regex.is_match(&file_name) {
-                                    continue; // 文件名不匹配正则表达式
-                                }
-                            }
-                        } else {
-                            if let Some(file_name) = entry.file_name().to_str() {
-                                if !regex.is_match(file_name) {
-                                    continue; // 文件名不匹配正则表达式
-                                }
-                            }
-                        }
+            // 不跟随时符号链接本身作为一个条目列出，特殊文件(设备、管道等)由过滤条件按系统文件处理
+            let special = attributes::is_special(&metadata);
+            if options.result_kind.includes_files() && search_root.reports(depth) && (metadata.is_file() || !follow || special) {
+
+                let modified = match timestamp_seconds(&metadata, TimeField::Modified) {
+                    Ok(modified) => modified,
+                    Err(e) => {
+                        report.record(&path, SearchOperation::ModifiedTime, &e);
+                        continue;
                     }
+                };
+
+                // 对文件求值过滤条件树，其中的内容条件会读取文件，记录匹配的行
+                // 未跟随的符号链接和特殊文件没有可以读取的内容
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                let mut candidate = Entry::new(&path, relative, &metadata, follow && !special, options.content_scan_limit, cancel);
+                let matched = predicate.matches(&mut candidate);
+                if cancel.is_cancelled() {
+                    break;
+                }
+                if let Some(e) = candidate.error.take() {
+                    report.record(&path, SearchOperation::ReadContent, &e);
+                    continue;
                 }
+                if !matched {
+                    continue;
+                }
+                let matches = candidate.matches;
+
+                let hash_value:String;
 
-                if filter(&metadata) {
-                    
-                    let hash_value:String;
-
-                    if unsafe{settings::SAVE_HASH} {
-                        let mut file = fs::File::open(&entry.path()).unwrap();
-                        let mut hasher = Sha256::new();
-                        let mut buffer = [0; 1024];
-                        while let Ok(bytes_read) = file.read(&mut buffer) {
-                            if bytes_read == 0 {
-                                break;
+                // 未跟随的符号链接和特殊文件没有可以计算哈希的内容(读取管道甚至会一直阻塞)
+                // 文件自上次计算以来没有变化时直接使用缓存中的哈希值
+                if options.save_hash && follow && !special {
+                    let algorithm = options.hash_algorithm;
+                    let hash = match context.hash_cache.as_ref().and_then(|cache| cache.get(&path, &metadata, algorithm)) {
+                        Some(hash) => Ok(hash),
+                        None => hash_file(&path, algorithm, cancel).inspect(|hash| {
+                            if let Some(cache) = &context.hash_cache {
+                                cache.insert(&path, &metadata, algorithm, hash);
                             }
-                            hasher.update(&buffer[..bytes_read]);
+                        }),
+                    };
+                    match hash {
+                        Ok(hash) => hash_value = hash,
+                        Err(_) if cancel.is_cancelled() => break,
+                        Err(e) => {
+                            report.record(&path, SearchOperation::Hash, &e);
+                            continue;
                         }
-                        let hash = hasher.finalize();
-                        hash_value = format!("{:x}", hash);
-                    } else {
-                        hash_value = String::new();
                     }
+                } else {
+                    hash_value = String::new();
+                }
 
-                    let file_info = SingleFileInformations {
-                        path,
-                        name: entry.file_name().into_string().unwrap(),
-                        size: metadata.len(),
-                        time: metadata.modified().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-                        hash: hash_value,
-                    };
+                let file_info = SingleFileInformations {
+                    path,
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    size: metadata.len(),
+                    time: modified,
+                    created: timestamp_seconds(&metadata, TimeField::Created).ok(),
+                    accessed: timestamp_seconds(&metadata, TimeField::Accessed).ok(),
+                    hash: hash_value,
+                    hash_algorithm: options.hash_algorithm,
+                    link_target,
+                    matches,
+                    is_dir: false,
+                    file_count: 0,
+                };
 
-                    result.push(file_info);
-                    
-                }
+                result.push(file_info);
             }
         }
     }
-    for handle in handles {
-        result.extend(handle.join().unwrap());
-    }
     result
 }
 
-fn filter(metadata: &fs::Metadata) -> bool {
-    // path.extension().map_or(false, |ext| ext == "rs")
-    // println!("Checking: {:?}", path);
-    // println!("{:?}",metadata);
-
-    // 检查是否跳过隐藏文件
-    if unsafe{!settings::SEARCH_HIDDEN_FILES} {
-        if metadata.file_attributes() & settings::FILE_ATTRIBUTE_HIDDEN != 0 {
-            return false; // Skip hidden files
+// 目录是否在排除列表中
+// 不含路径分隔符的项按目录名匹配(如 target)，绝对路径按完整路径匹配，其他按相对于搜索根目录的路径匹配
+fn is_excluded_directory(path: &Path, relative: &Path, excluded: &[String]) -> bool {
+    excluded.iter().any(|item| {
+        let item = Path::new(item);
+        if item.is_absolute() {
+            path == item
+        } else if item.components().count() == 1 {
+            path.file_name() == Some(item.as_os_str())
+        } else {
+            relative == item
         }
-    }
+    })
+}
 
-    // 检查文件大小限制
-    {
-        let file_size: u64 = metadata.len();
-        if unsafe{file_size > settings::SEARCH_FILESIZE_MAXIMUM_LIMIT} {
-            return false; // Skip files larger than the maximum limit
-        }
-        if unsafe{file_size < settings::SEARCH_FILESIZE_MINIMUM_LIMIT} {
-            return false; // Skip files smaller than the minimum limit
-        }
+// 读取文件的修改/创建/访问时间
+fn file_time(metadata: &fs::Metadata, field: TimeField) -> io::Result<Time> {
+    match field {
+        TimeField::Modified => metadata.modified(),
+        TimeField::Created => metadata.created(),
+        TimeField::Accessed => metadata.accessed(),
     }
+}
 
-    // 检查是否跳过只读文件
-    // 只读文件的属性是 FILE_ATTRIBUTE_READONLY
-    if unsafe{!settings::SEARCH_READONLY} {
-        if metadata.file_attributes() & settings::FILE_ATTRIBUTE_READONLY != 0 {
-            return false; // Skip read-only files
-        }
-    }
+// 转换为秒级时间戳，早于1970年的时间记为0
+fn to_seconds(time: Time) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
 
-    // 检查文件日期
-    if unsafe{settings::SEARCH_TIMELIMIT} {
-        if unsafe{settings::SEARCH_TIMELIMIT_CURRENTTIME_TYPE} {
-            if let Ok(modified) = metadata.modified() {
-                // 若比指定时间更新且查询的是旧文件则return
-                // 若比指定时间早且查询的是新文件则return
-                if (modified > Time::now() - Duration::from_secs(unsafe{settings::SEARCH_COMPARE_WITH_CURRENT_TIME_LIMIT})) ^ unsafe{settings::SEARCH_COMPARE_WITH_CURRENT_TIME_NEWER} {
-                    return false; // Skip files older than the specified limit
-                }
-            }
-        } else {
-            let modified = metadata.modified().unwrap().duration_since(UNIX_EPOCH).unwrap().as_secs();
-            if modified > unsafe{settings::SEARCH_MODIFY_TIME_MAXIMUM_LIMIT} {
-                return false;
-            }
-            if modified < unsafe{settings::SEARCH_MODIFY_TIME_MINIMUM_LIMIT} {
-                return false;
-            }
-        }
-    }
+// 读取文件的某个时间(秒级时间戳)
+fn timestamp_seconds(metadata: &fs::Metadata, field: TimeField) -> io::Result<u64> {
+    file_time(metadata, field).map(to_seconds)
+}
 
-    // 检查是否跳过系统文件
-    if unsafe{!settings::SEARCH_SYSTEM_FILES} {
-        if metadata.file_attributes() & settings::FILE_ATTRIBUTE_SYSTEM != 0 {
-            return false; // Skip system files
+// 用指定的算法计算文件的哈希值
+// 大文件的哈希可能需要较长时间，因此每读一块都检查一次搜索是否已被取消
+fn hash_file(path: &Path, algorithm: HashAlgorithm, cancel: &CancelToken) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = checksum::FileHasher::new(algorithm);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        if cancel.is_cancelled() {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "search cancelled"));
         }
+        let bytes_read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(bytes_read) => bytes_read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..bytes_read]);
     }
-
-    true
+    Ok(hasher.finish())
 }
 
-// This function searches for files in the given paths with the specified search depth and file regex.
+// This function searches for files in the given roots with the specified search depth and predicate.
 // It returns a vector of SingleFileInformations containing the file details.
-// 需要传入的参数: 向下搜索的深度search_depth , 正则表达式file_regex , 其他的参数通过settings.rs中的静态变量设置
-pub fn get_files(paths:&[PathBuf],search_depth:u8,file_regex: &String) -> Vec<SingleFileInformations> {
-    let mut all_found_files: Vec<SingleFileInformations> = Vec::new();
-    for current_path in paths {
-        all_found_files.extend(search_in_path(filter, &current_path , search_depth, &file_regex));
-    }
-    // 这里可以添加获取文件的逻辑
-    all_found_files
+// 需要传入的参数: 搜索根目录及各自的深度范围roots(见SearchRoot) , 每个文件都要满足的过滤条件树predicate(见predicate.rs) , 其他的参数通过options传入(见settings.rs中的SearchOptions)
+// predicate只决定遍历到的条目是否作为结果；options只控制遍历本身，剪枝不从predicate推导:
+// 隐藏目录(search_hidden_folders)、excluded_directories、匹配exclude_globs的目录和被忽略文件排除的目录不会进入，
+// 扩展名不符(include_extensions/exclude_extensions)的文件在读取元数据之前就被跳过，不会交给predicate求值
+// 因此options中的这些设置必须与predicate一致，或者保持默认值(不剪枝)，SearchHelper::perform_search_streaming用同一个SearchFilter构建两者
+// 目录遍历使用固定数量的工作线程(options.worker_threads)
+// 无法读取的路径不会中断搜索，而是记录在返回的SearchReport中
+// 通过cancel可以从其他线程中途停止搜索，此时返回已经找到的文件，report.cancelled为true
+#[allow(dead_code)] // 界面使用get_files_streaming边搜索边显示结果，这里是等待搜索结束的同步版本
+pub fn get_files(roots: &[SearchRoot], predicate: &Predicate, options: SearchOptions, cancel: &CancelToken) -> (Vec<SingleFileInformations>, SearchReport) {
+    let found_files = Mutex::new(Vec::new());
+    let progress = SearchProgress::default();
+    let report = get_files_streaming(roots, predicate, options, &progress, cancel, &|files| {
+        found_files.lock().unwrap().extend(files);
+    });
+    (found_files.into_inner().unwrap(), report)
+}
+
+// 与get_files相同，但不等待搜索结束:
+// 每搜索完一个目录就把其中找到的文件交给on_batch(在工作线程中调用)，并更新progress中的计数
+// 适合在后台线程中运行，边搜索边显示结果
+pub fn get_files_streaming(roots: &[SearchRoot], predicate: &Predicate, options: SearchOptions, progress: &SearchProgress, cancel: &CancelToken, on_batch: &(dyn Fn(Vec<SingleFileInformations>) + Sync)) -> SearchReport {
+    let mut report = run_worker_pool(predicate, roots, &options, progress, cancel, on_batch);
+    report.cancelled = cancel.is_cancelled();
+    report
 }
 
 // This function sorts the files based on selected sort type.
 // sort_type can be "name", "size", "time", or "path".
 // If reversed is true, it will sort in descending order.
-// 在整体代码中的行为:传入get_files或unique_files得到的Vec,将其按照指定方式排序
-pub fn sort_files(files: &mut Vec<SingleFileInformations>, sort_type: String, reversed: bool) {
+// 在整体代码中的行为:传入get_files或get_files_streaming得到的Vec,将其按照指定方式排序
+pub fn sort_files(files: &mut [SingleFileInformations], sort_type: String, reversed: bool) {
     match sort_type.as_str() {
         "name" => files.sort_by(|a, b| a.name.cmp(&b.name)),
-        "size" => files.sort_by(|a, b| a.size.cmp(&b.size)),
-        "time" => files.sort_by(|a, b| a.time.cmp(&b.time)),
+        "size" => files.sort_by_key(|a| a.size),
+        "time" => files.sort_by_key(|a| a.time),
+        "created" => files.sort_by_key(|a| a.created),
+        "accessed" => files.sort_by_key(|a| a.accessed),
         "path" => files.sort_by(|a, b| a.path.cmp(&b.path)),
         _ => (),
     }
@@ -231,17 +659,6 @@ pub fn sort_files(files: &mut Vec<SingleFileInformations>, sort_type: String, re
     }
 }
 
-// This function removes duplicate files based on their hash.
-// It sorts the files by hash and then removes duplicates.
-// 整体代码中的行为:若选择使用SHA256哈希值来去重,則在get_files後調用此函數作為新的Vec
-pub fn unique_files(files: &mut Vec<SingleFileInformations>) {
-    if files.is_empty() || files[0].hash.is_empty() {
-        return;
-    }
-    files.sort_by(|a, b| a.hash.cmp(&b.hash));
-    files.dedup_by(|a, b| a.hash == b.hash);
-}
-
 // This function reports the found files to a JSON file named "search_result.json".
 // 整体代码中的行为:点按导出结果按钮后将搜索结果写入search_result.json文件
 pub fn export_found_files(found_files: &[SingleFileInformations]) {
@@ -249,13 +666,90 @@ pub fn export_found_files(found_files: &[SingleFileInformations]) {
     std::fs::write("search_result.json", json_str).expect("Unable to write result to file");
 }
 
+// 去掉位于列表中其他目录之内的条目，它们会随所在的目录一起被复制、移动或删除
+fn outermost(files: &[SingleFileInformations]) -> Vec<&SingleFileInformations> {
+    let directories: Vec<&Path> = files.iter()
+        .filter(|file| file.is_dir)
+        .map(|file| file.path.as_path())
+        .collect();
+    files.iter()
+        .filter(|file| !directories.iter().any(|directory| file.path != *directory && file.path.starts_with(directory)))
+        .collect()
+}
+
+// 复制一个条目，目录连同其中的内容一起复制
+fn copy_entry(file: &SingleFileInformations, dest_path: &Path) -> io::Result<()> {
+    if file.is_dir {
+        if dest_path.starts_with(&file.path) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "不能把目录复制到它自身之中"));
+        }
+        copy_directory(&file.path, dest_path)
+    } else {
+        fs::copy(&file.path, dest_path).map(|_| ())
+    }
+}
+
+// 递归复制目录，其中的符号链接复制为指向相同目标的链接，已存在的文件会被覆盖
+fn copy_directory(source: &Path, destination: &Path) -> io::Result<()> {
+    fs::create_dir_all(destination)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let target = destination.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_directory(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    let target = fs::read_link(source)?;
+    let _ = fs::remove_file(destination);
+    std::os::unix::fs::symlink(target, destination)
+}
+
+#[cfg(windows)]
+fn copy_symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    let target = fs::read_link(source)?;
+    let _ = fs::remove_file(destination);
+    if fs::metadata(source).map(|metadata| metadata.is_dir()).unwrap_or(false) {
+        std::os::windows::fs::symlink_dir(target, destination)
+    } else {
+        std::os::windows::fs::symlink_file(target, destination)
+    }
+}
+
+/// 逐个删除或映射搜索结果的结果，一个条目失败不影响其余条目
+#[derive(Debug, Default)]
+pub struct FileOperationReport {
+    pub processed: Vec<PathBuf>, // 已处理的条目
+    pub skipped: Vec<PathBuf>, // 映射时不在起始文件夹中、没有处理的条目
+    pub errors: Vec<(PathBuf, String)>, // 处理失败的条目
+}
+
+impl FileOperationReport {
+    fn record(&mut self, path: &Path, result: io::Result<()>) {
+        match result {
+            Ok(()) => self.processed.push(path.to_path_buf()),
+            Err(e) => self.errors.push((path.to_path_buf(), e.to_string())),
+        }
+    }
+}
+
 // This function copies files in the list to the destination directory.
 // It will overwrite files in the destination if they already exist.
 // 行为：点按复制按钮后将搜索结果复制到指定目录，调用时要用 &path::absolute("地址").unwrap().to_path_buf() 这种写法
-pub fn copy_files(files: &[SingleFileInformations], destination: &PathBuf) -> io::Result<()> {
-    for file in files {
+// 目录连同其中的内容一起复制，已经包含在其他选中目录中的条目不再单独复制
+pub fn copy_files(files: &[SingleFileInformations], destination: &Path) -> io::Result<()> {
+    for file in outermost(files) {
         let dest_path = destination.join(&file.name);
-        fs::copy(&file.path, dest_path)?;
+        copy_entry(file, &dest_path)?;
     }
     Ok(())
 }
@@ -263,8 +757,9 @@ pub fn copy_files(files: &[SingleFileInformations], destination: &PathBuf) -> io
 // This function moves files in the list to the destination directory.
 // It will overwrite files in the destination if they already exist.
 // 行为: 点按移动按钮后将搜索结果移动到指定目录，同上（覆盖）
-pub fn move_files(files: &[SingleFileInformations], destination: &PathBuf) -> io::Result<()> {
-    for file in files {
+// 目录整个移动，已经包含在其他选中目录中的条目不再单独移动
+pub fn move_files(files: &[SingleFileInformations], destination: &Path) -> io::Result<()> {
+    for file in outermost(files) {
         let dest_path = destination.join(&file.name);
         fs::rename(&file.path, dest_path)?;
     }
@@ -272,15 +767,16 @@ pub fn move_files(files: &[SingleFileInformations], destination: &PathBuf) -> io
 }
 
 // delete files in the list
-// This function will remove the files from the filesystem.
-// It does not check if the files exist, so it may return an error if a file does not exist.
+// This function will move the files to the trash.
+// It does not check if the files exist, so a file that does not exist is reported as an error.
 // 行为: 删除，后略
-pub fn delete_files(files: &[SingleFileInformations]) -> io::Result<()> {
-    for file in files {
-        // fs::remove_file(&file.path)?;
-        trash::delete(&file.path).expect("Failed to delete file");
+// 目录连同其中的内容一起移到回收站；某个条目失败时记录在report中，继续处理其余条目
+pub fn delete_files(files: &[SingleFileInformations]) -> FileOperationReport {
+    let mut report = FileOperationReport::default();
+    for file in outermost(files) {
+        report.record(&file.path, trash::delete(&file.path).map_err(io::Error::other));
     }
-    Ok(())
+    report
 }
 
 // This function copies files in the list from the source directory to the destination directory.
@@ -288,22 +784,240 @@ pub fn delete_files(files: &[SingleFileInformations]) -> io::Result<()> {
 // 行为: 维持原有目录结构的复制，点按映射按钮后将搜索结果复制到指定目录
 // 将搜索到的文件在source中的部分全部维持原文件夹架构复制到destination (由于有多个搜索源故需指定source防止重名文件冲突)
 // 注: 传入的source和destination必须是绝对路径,files如果按照上面的实现的话内部的path也是绝对路径
-pub fn mapping_files(files: &[SingleFileInformations], source: &PathBuf, destination: &PathBuf) {
-    for file in files {
+// 无法创建destination时返回错误；单个条目失败时记录在report中，继续处理其余条目
+pub fn mapping_files(files: &[SingleFileInformations], source: &PathBuf, destination: &Path) -> io::Result<FileOperationReport> {
+    fs::create_dir_all(destination)?;
+    let mut report = FileOperationReport::default();
+    for file in outermost(files) {
         let relative_path = match file.path.strip_prefix(source) {
             Ok(relative) => relative.to_path_buf(),
             Err(_) => {
-                continue; // Skip this file if it doesn't match the source path
+                report.skipped.push(file.path.clone()); // Skip this file if it doesn't match the source path
+                continue;
             }
         };
         let dest_path = destination.join(relative_path);
         
-        // Create the parent directory if it doesn't exist
-        if let Some(parent) = dest_path.parent() {
-            fs::create_dir_all(parent).expect("Failed to create parent directory");
+        // Create the parent directory if it doesn't exist, then copy the file (目录连同其中的内容)
+        let result = match dest_path.parent() {
+            Some(parent) => fs::create_dir_all(parent),
+            None => Ok(()),
+        }.and_then(|()| copy_entry(file, &dest_path));
+        report.record(&file.path, result);
+    }
+    Ok(report)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use settings::ResultKind;
+
+    fn file_info(path: &Path) -> SingleFileInformations {
+        let metadata = fs::symlink_metadata(path).unwrap();
+        SingleFileInformations {
+            path: path.to_path_buf(),
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            size: metadata.len(),
+            time: 0,
+            hash: String::new(),
+            hash_algorithm: HashAlgorithm::default(),
+            link_target: None,
+            matches: Vec::new(),
+            created: None,
+            accessed: None,
+            is_dir: metadata.is_dir(),
+            file_count: 0,
         }
-        
-        // Copy the file
-        fs::copy(&file.path, dest_path).expect("Failed to copy file");
     }
-}
\ No newline at end of file
+
+    // 在root中创建文件，路径中的目录自动创建
+    fn create_files(root: &Path, paths: &[&str]) {
+        for path in paths {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, path.to_string_lossy().as_bytes()).unwrap();
+        }
+    }
+
+    // 搜索所有文件，返回排序后相对于第一个根目录的路径
+    fn search(roots: &[SearchRoot], options: SearchOptions) -> (Vec<String>, SearchReport) {
+        let (files, report) = get_files(roots, &Predicate::True, options, &CancelToken::new());
+        (relative_paths(&roots[0].path, &files), report)
+    }
+
+    fn relative_paths(root: &Path, files: &[SingleFileInformations]) -> Vec<String> {
+        let mut paths: Vec<String> = files.iter()
+            .map(|file| file.path.strip_prefix(root).unwrap_or(&file.path).to_string_lossy().replace('\\', "/"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn worker_pool_finds_every_file_once() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut expected = Vec::new();
+        for a in 0..6 {
+            for b in 0..4 {
+                for file in 0..3 {
+                    expected.push(format!("d{}/e{}/f{}.txt", a, b, file));
+                }
+            }
+            expected.push(format!("d{}/top.txt", a));
+        }
+        expected.push("root.txt".to_string());
+        expected.sort();
+        let paths: Vec<&str> = expected.iter().map(|path| path.as_str()).collect();
+        create_files(directory.path(), &paths);
+
+        let roots = [SearchRoot::new(directory.path().to_path_buf())];
+        for threads in [1, 2, 8] {
+            let options = SearchOptions { worker_threads: threads, ..SearchOptions::default() };
+            let (found, report) = search(&roots, options);
+            assert_eq!(found, expected, "{} threads", threads);
+            assert!(report.errors.is_empty());
+            assert!(!report.cancelled);
+        }
+
+        // 每个目录搜索一次，找到的文件分批交给调用者
+        let progress = SearchProgress::default();
+        let batches = Mutex::new(0);
+        let options = SearchOptions { worker_threads: 4, ..SearchOptions::default() };
+        get_files_streaming(&roots, &Predicate::True, options, &progress, &CancelToken::new(), &|_| *batches.lock().unwrap() += 1);
+        assert_eq!(progress.directories_scanned.load(Ordering::Relaxed), 1 + 6 + 6 * 4);
+        assert_eq!(progress.files_matched.load(Ordering::Relaxed), expected.len());
+        assert_eq!(*batches.lock().unwrap(), 1 + 6 + 6 * 4);
+    }
+
+    #[test]
+    fn unreadable_directories_are_reported() {
+        let directory = tempfile::tempdir().unwrap();
+        create_files(directory.path(), &["a.txt", "locked/b.txt", "open/c.txt"]);
+        let missing = directory.path().join("missing");
+        let roots = [SearchRoot::new(directory.path().to_path_buf()), SearchRoot::new(missing.clone())];
+
+        // 不存在的根目录记录在报告中，不影响其他根目录
+        let (found, report) = search(&roots, SearchOptions::default());
+        assert_eq!(found, ["a.txt", "locked/b.txt", "open/c.txt"]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].path, missing);
+        assert_eq!(report.errors[0].operation, SearchOperation::ReadDirectory);
+        assert_eq!(report.errors[0].kind, io::ErrorKind::NotFound);
+
+        // 没有读取权限的目录被跳过，其余目录照常搜索(root用户不受权限限制，此时不检查)
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let locked = directory.path().join("locked");
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+            if fs::read_dir(&locked).is_err() {
+                let (found, report) = search(&roots[..1], SearchOptions::default());
+                assert_eq!(found, ["a.txt", "open/c.txt"]);
+                assert_eq!(report.errors.len(), 1);
+                assert_eq!(report.errors[0].path, locked);
+                assert_eq!(report.errors[0].kind, io::ErrorKind::PermissionDenied);
+                assert_eq!(report.errors[0].to_string(), format!("{} (读取目录失败: permission denied)", locked.display()));
+            }
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn cancelled_search_keeps_partial_results() {
+        let directory = tempfile::tempdir().unwrap();
+        create_files(directory.path(), &["a.txt", "b.txt", "sub1/c.txt", "sub2/d.txt", "sub2/deeper/e.txt"]);
+        let roots = [SearchRoot::new(directory.path().to_path_buf())];
+
+        // 一个工作线程时，根目录中的文件交出后立即取消，不再搜索子目录
+        let cancel = CancelToken::new();
+        let found = Mutex::new(Vec::new());
+        let options = SearchOptions { worker_threads: 1, ..SearchOptions::default() };
+        let report = get_files_streaming(&roots, &Predicate::True, options, &SearchProgress::default(), &cancel, &|files| {
+            found.lock().unwrap().extend(files);
+            cancel.cancel();
+        });
+        assert!(report.cancelled);
+        assert_eq!(relative_paths(directory.path(), &found.into_inner().unwrap()), ["a.txt", "b.txt"]);
+
+        // 开始之前就已取消时没有结果
+        let (files, report) = get_files(&roots, &Predicate::True, SearchOptions::default(), &cancel);
+        assert!(report.cancelled);
+        assert!(files.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_are_searched_once() {
+        let directory = tempfile::tempdir().unwrap();
+        create_files(directory.path(), &["root.txt", "a/file.txt"]);
+        // 指回根目录的循环链接，以及指向同一目录树的重复链接
+        std::os::unix::fs::symlink(directory.path(), directory.path().join("a").join("loop")).unwrap();
+        std::os::unix::fs::symlink(directory.path().join("a"), directory.path().join("b")).unwrap();
+        let roots = [SearchRoot::new(directory.path().to_path_buf())];
+
+        // 跟随时每个目录只搜索一次，a和b中先被搜索的一个列出file.txt
+        let options = SearchOptions { symlink_mode: SymlinkMode::Follow, worker_threads: 4, ..SearchOptions::default() };
+        let (found, report) = search(&roots, options);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert_eq!(found[1], "root.txt");
+        assert!(found[0] == "a/file.txt" || found[0] == "b/file.txt", "{:?}", found);
+
+        // 不跟随时链接本身作为结果
+        let options = SearchOptions { symlink_mode: SymlinkMode::List, ..SearchOptions::default() };
+        assert_eq!(search(&roots, options).0, ["a/file.txt", "a/loop", "b", "root.txt"]);
+        let options = SearchOptions { symlink_mode: SymlinkMode::Skip, ..SearchOptions::default() };
+        assert_eq!(search(&roots, options).0, ["a/file.txt", "root.txt"]);
+    }
+
+    #[test]
+    fn each_root_has_its_own_depth_range() {
+        let directory = tempfile::tempdir().unwrap();
+        create_files(directory.path(), &[
+            "one/d1.txt", "one/x/d2.txt", "one/x/y/d3.txt", "one/x/y/z/d4.txt",
+            "two/d1.txt", "two/x/d2.txt",
+        ]);
+        let roots = [
+            SearchRoot::with_depth(directory.path().join("one"), 2, 3),
+            SearchRoot::with_depth(directory.path().join("two"), 0, 1),
+        ];
+        let (files, report) = get_files(&roots, &Predicate::True, SearchOptions::default(), &CancelToken::new());
+        assert!(report.errors.is_empty());
+        assert_eq!(relative_paths(directory.path(), &files), ["one/x/d2.txt", "one/x/y/d3.txt", "two/d1.txt"]);
+
+        // 浅于min_depth的目录不作为结果，但仍会进入其中搜索
+        let roots = [SearchRoot::with_depth(directory.path().join("one"), 2, 2)];
+        let options = SearchOptions { result_kind: ResultKind::Both, ..SearchOptions::default() };
+        let (found, _) = search(&roots, options);
+        assert_eq!(found, ["x/d2.txt", "x/y"]);
+    }
+
+    #[test]
+    fn mapping_reports_each_entry() {
+        let source = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        fs::write(source.path().join("a.txt"), b"a").unwrap();
+        fs::create_dir(source.path().join("sub")).unwrap();
+        fs::write(source.path().join("sub").join("b.txt"), b"b").unwrap();
+        fs::write(outside.path().join("c.txt"), b"c").unwrap();
+        // 目标中已有名为sub的文件，无法创建sub目录
+        fs::write(destination.path().join("sub"), b"").unwrap();
+
+        let files = vec![
+            file_info(&source.path().join("a.txt")),
+            file_info(&source.path().join("sub").join("b.txt")),
+            file_info(&outside.path().join("c.txt")),
+        ];
+        let report = mapping_files(&files, &source.path().to_path_buf(), destination.path()).unwrap();
+        assert_eq!(report.processed, vec![source.path().join("a.txt")]);
+        assert_eq!(report.skipped, vec![outside.path().join("c.txt")]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, source.path().join("sub").join("b.txt"));
+        assert_eq!(fs::read(destination.path().join("a.txt")).unwrap(), b"a");
+
+        // 无法创建目标文件夹时整个映射失败
+        let blocked = destination.path().join("sub").join("mapped");
+        assert!(mapping_files(&files, &source.path().to_path_buf(), &blocked).is_err());
+    }
+}
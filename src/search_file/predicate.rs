@@ -0,0 +1,346 @@
+// 过滤条件树
+// 叶子是对单个文件的一项检查(名称、路径、大小、时间、类型、内容……)，And/Or/Not把它们任意组合，
+// 例如"(大于5MB的jpg) 或 (任意大小的raw)，但不在/backup下":
+//   Predicate::and(vec![
+//       Predicate::or(vec![
+//           Predicate::and(vec![Predicate::extensions(&["jpg"]), Predicate::size(5 * 1024 * 1024, u64::MAX)]),
+//           Predicate::extensions(&["raw"]),
+//       ]),
+//       Predicate::not(Predicate::Path(Regex::new("^/backup/").unwrap())),
+//   ])
+// 工作线程对每个文件求值一次；SearchOptions中的各项过滤设置(以及SearchFilter)只是构建条件树的一种方式
+// 条件树只决定遍历到的条目是否作为结果，遍历时的剪枝不从条件树推导: 进入哪些目录、提前跳过哪些文件
+// 由SearchOptions决定(隐藏目录、排除目录、排除通配符、忽略文件、扩展名)，见search_file::get_files_streaming
+// 目录作为结果时也对目录求值，此时大小条件比较的是目录中所有文件的总大小
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use globset::GlobSet;
+use regex::Regex;
+
+use super::{attributes, content, timestamp_seconds, to_seconds, CancelToken};
+use super::content::ContentMatch;
+use super::directories::{DirectoryTotals, TotalsCache};
+use super::file_types::{extension_candidates, normalize_extension};
+use super::glob::build_glob_set;
+use super::rules::RuleSet;
+use super::settings::{ResultKind, SearchOptions, TimeField};
+
+/// 文件属性
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    Hidden,
+    Readonly,
+    System,
+}
+
+/// 过滤条件树
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    True, // 总是满足
+    And(Vec<Predicate>), // 全部满足，按顺序求值，遇到不满足的条件立即停止
+    Or(Vec<Predicate>), // 满足任意一个
+    Not(Box<Predicate>),
+    Name(Regex), // 文件名匹配正则表达式
+    Path(Regex), // 完整路径匹配正则表达式
+    Rules(Arc<RuleSet>), // 多条正则表达式规则，编译一次后在工作线程间共享
+    Glob(GlobSet), // 相对于搜索根目录的路径匹配任意一个通配符
+    Size { minimum: u64, maximum: u64 }, // 大小在[minimum, maximum]字节之间，目录按其中文件的总大小计算
+    Time { field: TimeField, minimum: u64, maximum: u64 }, // 时间(秒级时间戳)在[minimum, maximum]之间，文件系统不提供该时间时不满足
+    Extension(HashSet<String>), // 类型: 扩展名(小写，不带".")属于该集合
+    Attribute(Attribute), // 带有该属性
+    Content(regex::bytes::Regex), // 文件内容中有匹配的行，读取文件，开销最大
+    Directory, // 是目录
+    Empty, // 长度为0的普通文件，或者除了junk文件之外没有任何文件的目录
+}
+
+/// 求值时的一个文件
+pub struct Entry<'a> {
+    pub path: &'a Path,
+    pub relative: &'a Path, // 相对于搜索根目录的路径
+    pub metadata: &'a fs::Metadata,
+    pub readable: bool, // 是否有可以读取的内容，未跟随的符号链接和特殊文件没有
+    pub content_limit: u64, // 搜索内容时最多读取的字节数
+    pub cancel: &'a CancelToken,
+    pub matches: Vec<ContentMatch>, // 内容条件找到的匹配行
+    pub error: Option<io::Error>, // 读取内容或统计目录时遇到的错误
+    pub totals: Option<DirectoryTotals>, // 目录中文件的汇总，需要时才统计
+    totals_cache: Option<&'a TotalsCache>,
+}
+
+impl<'a> Entry<'a> {
+    pub fn new(path: &'a Path, relative: &'a Path, metadata: &'a fs::Metadata, readable: bool, content_limit: u64, cancel: &'a CancelToken) -> Self {
+        Self {
+            path,
+            relative,
+            metadata,
+            readable,
+            content_limit,
+            cancel,
+            matches: Vec::new(),
+            error: None,
+            totals: None,
+            totals_cache: None,
+        }
+    }
+
+    // 条目是目录时，通过cache统计目录中的文件
+    pub fn with_totals(mut self, cache: &'a TotalsCache) -> Self {
+        self.totals_cache = Some(cache);
+        self
+    }
+
+    // 目录中文件的汇总，不是目录或统计失败时为None
+    pub fn directory_totals(&mut self) -> Option<DirectoryTotals> {
+        if self.totals.is_none() && self.metadata.is_dir() {
+            let cache = self.totals_cache?;
+            match cache.measure(self.path, self.cancel) {
+                Ok(totals) => self.totals = Some(totals),
+                Err(e) => {
+                    self.error.get_or_insert(e);
+                }
+            }
+        }
+        self.totals
+    }
+
+    // 文件的大小或目录中文件的总大小
+    fn size(&mut self) -> Option<u64> {
+        if self.metadata.is_dir() && self.totals_cache.is_some() {
+            self.directory_totals().map(|totals| totals.size)
+        } else {
+            Some(self.metadata.len())
+        }
+    }
+
+    // 在文件内容中搜索，读取失败时记录错误并视为不匹配
+    fn search_content(&mut self, regex: &regex::bytes::Regex) -> bool {
+        if !self.readable {
+            return false;
+        }
+        match content::search_content(self.path, regex, self.content_limit, self.cancel) {
+            Ok(found) if found.is_empty() => false,
+            Ok(found) => {
+                self.matches.extend(found);
+                true
+            },
+            Err(e) => {
+                self.error.get_or_insert(e);
+                false
+            }
+        }
+    }
+}
+
+impl Predicate {
+    // 只有一个条件时直接返回该条件
+    pub fn and(mut children: Vec<Predicate>) -> Self {
+        match children.len() {
+            0 => Predicate::True,
+            1 => children.remove(0),
+            _ => Predicate::And(children),
+        }
+    }
+
+    pub fn or(mut children: Vec<Predicate>) -> Self {
+        match children.len() {
+            1 => children.remove(0),
+            _ => Predicate::Or(children),
+        }
+    }
+
+    pub fn not(child: Predicate) -> Self {
+        Predicate::Not(Box::new(child))
+    }
+
+    pub fn size(minimum: u64, maximum: u64) -> Self {
+        Predicate::Size { minimum, maximum }
+    }
+
+    // 扩展名可以带"."或"*."
+    pub fn extensions<S: AsRef<str>>(extensions: &[S]) -> Self {
+        Predicate::Extension(extensions.iter().map(|extension| normalize_extension(extension.as_ref())).collect())
+    }
+
+    // 不合法的通配符会被忽略
+    pub fn globs(patterns: &[String]) -> Self {
+        Predicate::Glob(build_glob_set(patterns))
+    }
+
+    // 把SearchOptions中对单个文件的各项过滤设置转换为条件树
+    // 便宜的检查放在前面，读取文件内容的检查放在最后
+    // 结果中包含目录时，扩展名、属性和内容条件只作用于文件，名称、路径、大小和时间条件同样作用于目录
+    pub fn from_options(options: &SearchOptions, file_regex: &str) -> Result<Self, regex::Error> {
+        let mut conditions = Vec::new();
+        let files_only = |condition: Predicate| match options.result_kind {
+            ResultKind::Files => condition,
+            _ => Predicate::or(vec![Predicate::Directory, condition]),
+        };
+
+        if !file_regex.is_empty() && !options.search_content {
+            let regex = Regex::new(file_regex)?;
+            let condition = if options.regex_contain_path { Predicate::Path(regex) } else { Predicate::Name(regex) };
+            conditions.push(if options.regex_invert { Predicate::not(condition) } else { condition });
+        }
+        let rules = RuleSet::new(&options.regex_rules, options.rule_mode)?;
+        if !rules.is_empty() {
+            conditions.push(Predicate::Rules(Arc::new(rules)));
+        }
+        if !options.include_globs.is_empty() {
+            conditions.push(Predicate::globs(&options.include_globs));
+        }
+        if !options.exclude_globs.is_empty() {
+            conditions.push(Predicate::not(Predicate::globs(&options.exclude_globs)));
+        }
+        if !options.include_extensions.is_empty() {
+            conditions.push(files_only(Predicate::extensions(&options.include_extensions)));
+        }
+        if !options.exclude_extensions.is_empty() {
+            conditions.push(files_only(Predicate::not(Predicate::extensions(&options.exclude_extensions))));
+        }
+        if !options.search_hidden_files {
+            conditions.push(files_only(Predicate::not(Predicate::Attribute(Attribute::Hidden))));
+        }
+        // 不限制大小时不添加条件，目录不需要为此统计
+        if options.search_filesize_minimum_limit > 0 || options.search_filesize_maximum_limit < u64::MAX {
+            conditions.push(Predicate::size(options.search_filesize_minimum_limit, options.search_filesize_maximum_limit));
+        }
+        if !options.search_readonly {
+            conditions.push(files_only(Predicate::not(Predicate::Attribute(Attribute::Readonly))));
+        }
+        if options.search_timelimit {
+            conditions.push(time_condition(options));
+        }
+        if !options.search_system_files {
+            conditions.push(files_only(Predicate::not(Predicate::Attribute(Attribute::System))));
+        }
+
+        // 目录需要遍历其中的内容才能判断是否为空
+        if options.find_empty {
+            conditions.push(Predicate::Empty);
+        }
+
+        if !file_regex.is_empty() && options.search_content {
+            let condition = Predicate::Content(regex::bytes::Regex::new(file_regex)?);
+            conditions.push(files_only(if options.regex_invert { Predicate::not(condition) } else { condition }));
+        }
+        Ok(Predicate::and(conditions))
+    }
+
+    // 判断文件是否满足条件
+    // 内容条件找到的匹配行和读取内容时的错误记录在entry中
+    pub fn matches(&self, entry: &mut Entry) -> bool {
+        match self {
+            Predicate::True => true,
+            Predicate::And(children) => children.iter().all(|child| child.matches(entry)),
+            Predicate::Or(children) => children.iter().any(|child| child.matches(entry)),
+            Predicate::Not(child) => !child.matches(entry),
+            Predicate::Name(regex) => match entry.path.file_name() {
+                Some(name) => regex.is_match(&name.to_string_lossy()),
+                None => false,
+            },
+            Predicate::Path(regex) => regex.is_match(&entry.path.to_string_lossy()),
+            Predicate::Rules(rules) => rules.matches(entry.path),
+            Predicate::Glob(globs) => globs.is_match(entry.relative),
+            Predicate::Size { minimum, maximum } => match entry.size() {
+                Some(size) => (*minimum..=*maximum).contains(&size),
+                None => false,
+            },
+            Predicate::Time { field, minimum, maximum } => match timestamp_seconds(entry.metadata, *field) {
+                Ok(seconds) => (*minimum..=*maximum).contains(&seconds),
+                Err(_) => false,
+            },
+            Predicate::Extension(extensions) => extension_candidates(entry.path)
+                .iter()
+                .any(|extension| extensions.contains(extension)),
+            Predicate::Attribute(attribute) => match attribute {
+                Attribute::Hidden => attributes::is_hidden(entry.path, entry.metadata),
+                Attribute::Readonly => attributes::is_readonly(entry.path, entry.metadata),
+                Attribute::System => attributes::is_system(entry.metadata),
+            },
+            Predicate::Content(regex) => entry.search_content(regex),
+            Predicate::Directory => entry.metadata.is_dir(),
+            Predicate::Empty => {
+                if entry.metadata.is_dir() {
+                    entry.directory_totals().is_some_and(|totals| totals.is_empty())
+                } else {
+                    entry.metadata.is_file() && entry.metadata.len() == 0
+                }
+            },
+        }
+    }
+}
+
+// 时间限制: 第一种是距离现在的时间，在构建条件时换算为具体的时间点；第二种是时间戳的范围
+fn time_condition(options: &SearchOptions) -> Predicate {
+    let field = options.search_time_field;
+    if options.search_timelimit_currenttime_type {
+        let cutoff = SystemTime::now()
+            .checked_sub(Duration::from_secs(options.search_compare_with_current_time_limit))
+            .map(to_seconds)
+            .unwrap_or(0);
+        if options.search_compare_with_current_time_newer {
+            Predicate::Time { field, minimum: cutoff, maximum: u64::MAX }
+        } else {
+            Predicate::Time { field, minimum: 0, maximum: cutoff }
+        }
+    } else {
+        Predicate::Time {
+            field,
+            minimum: options.search_modify_time_minimum_limit,
+            maximum: options.search_modify_time_maximum_limit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 对directory中的每个文件求值，返回满足条件的文件(相对路径)
+    fn matching(predicate: &Predicate, directory: &Path, files: &[&str]) -> Vec<String> {
+        let cancel = CancelToken::new();
+        files.iter()
+            .filter(|relative| {
+                let path = directory.join(relative);
+                let metadata = fs::metadata(&path).unwrap();
+                let mut entry = Entry::new(&path, Path::new(relative), &metadata, true, u64::MAX, &cancel);
+                predicate.matches(&mut entry)
+            })
+            .map(|relative| relative.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn nested_and_or_not_tree() {
+        let directory = tempfile::tempdir().unwrap();
+        let files = ["big.jpg", "small.jpg", "any.raw", "backup/big.jpg", "backup/any.raw", "notes.txt"];
+        fs::create_dir(directory.path().join("backup")).unwrap();
+        for file in files {
+            let size = if file.ends_with("big.jpg") { 10 } else { 1 };
+            fs::write(directory.path().join(file), vec![b'x'; size]).unwrap();
+        }
+
+        // (不小于5字节的jpg) 或 (任意大小的raw)，但不在backup下
+        let predicate = Predicate::and(vec![
+            Predicate::or(vec![
+                Predicate::and(vec![Predicate::extensions(&["jpg"]), Predicate::size(5, u64::MAX)]),
+                Predicate::extensions(&["raw"]),
+            ]),
+            Predicate::not(Predicate::Glob(build_glob_set(&["backup/**".to_string()]))),
+        ]);
+        assert_eq!(matching(&predicate, directory.path(), &files), ["big.jpg", "any.raw"]);
+
+        // 取反整棵树
+        let predicate = Predicate::not(predicate);
+        assert_eq!(matching(&predicate, directory.path(), &files), ["small.jpg", "backup/big.jpg", "backup/any.raw", "notes.txt"]);
+
+        // 空的And总是满足，空的Or总是不满足
+        assert_eq!(matching(&Predicate::and(Vec::new()), directory.path(), &files).len(), files.len());
+        assert!(matching(&Predicate::or(Vec::new()), directory.path(), &files).is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+// 多条正则表达式规则
+// 规则按顺序排列，每条规则匹配文件的某一部分(文件名、完整路径、父文件夹名、扩展名)，并决定包含还是排除该文件
+// 按顺序匹配时与.gitignore类似，最后一条匹配的规则决定结果；没有规则匹配时，只要存在包含规则就排除该文件，否则保留
+// 例如 [包含 文件名 \.rs$, 排除 父文件夹名 ^tests?$] 找出所有不在test(s)目录中的rs文件
+// 全部匹配时与顺序无关，文件必须匹配每一条包含规则且不匹配任何排除规则，查询框中的多个条件使用这种方式
+
+use std::path::Path;
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+
+/// 规则匹配文件的哪一部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleTarget {
+    #[default]
+    Name, // 文件名
+    Path, // 完整路径
+    Parent, // 所在文件夹的名称
+    Extension, // 扩展名(最后一个"."之后的部分，不带"."，没有扩展名时为空)
+}
+
+impl RuleTarget {
+    // 从文件路径中取出该部分的文本
+    fn text(&self, path: &Path) -> String {
+        let text = match self {
+            RuleTarget::Name => path.file_name(),
+            RuleTarget::Path => Some(path.as_os_str()),
+            RuleTarget::Parent => path.parent().and_then(|parent| parent.file_name()),
+            RuleTarget::Extension => path.extension(),
+        };
+        text.map(|text| text.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+}
+
+/// 多条规则的组合方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleMode {
+    #[default]
+    Ordered, // 最后一条匹配的规则决定包含还是排除
+    All, // 所有包含规则都匹配且没有排除规则匹配
+}
+
+/// 一条正则表达式规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegexRule {
+    pub pattern: String,
+    pub target: RuleTarget,
+    pub exclude: bool, // 匹配时排除文件，否则包含
+    pub case_insensitive: bool, // 忽略大小写
+}
+
+impl RegexRule {
+    pub fn include(pattern: &str, target: RuleTarget) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            target,
+            exclude: false,
+            case_insensitive: false,
+        }
+    }
+
+    pub fn exclude(pattern: &str, target: RuleTarget) -> Self {
+        Self { exclude: true, ..Self::include(pattern, target) }
+    }
+
+    // 实际编译的正则表达式，忽略大小写时加上(?i)
+    fn effective_pattern(&self) -> String {
+        if self.case_insensitive {
+            format!("(?i){}", self.pattern)
+        } else {
+            self.pattern.clone()
+        }
+    }
+
+    // 单独编译这条规则，用于在界面中检查输入
+    pub fn validate(&self) -> Result<(), regex::Error> {
+        Regex::new(&self.effective_pattern()).map(|_| ())
+    }
+}
+
+/// 编译后的规则
+/// 同一目标的规则编译为一个RegexSet，对每个文件每个目标只匹配一次；搜索时通过Arc在工作线程间共享
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    targets: Vec<(RuleTarget, RegexSet, Vec<usize>)>, // 目标、该目标的规则、每条规则在原列表中的位置
+    exclude: Vec<bool>, // 原列表中每条规则是否为排除规则
+    has_include: bool,
+    mode: RuleMode,
+}
+
+impl RuleSet {
+    // 模式为空的规则会被忽略
+    pub fn new(rules: &[RegexRule], mode: RuleMode) -> Result<Self, regex::Error> {
+        let rules: Vec<&RegexRule> = rules.iter().filter(|rule| !rule.pattern.is_empty()).collect();
+        let mut targets = Vec::new();
+        for target in [RuleTarget::Name, RuleTarget::Path, RuleTarget::Parent, RuleTarget::Extension] {
+            let indices: Vec<usize> = (0..rules.len()).filter(|&index| rules[index].target == target).collect();
+            if indices.is_empty() {
+                continue;
+            }
+            let set = RegexSet::new(indices.iter().map(|&index| rules[index].effective_pattern()))?;
+            targets.push((target, set, indices));
+        }
+        Ok(Self {
+            targets,
+            exclude: rules.iter().map(|rule| rule.exclude).collect(),
+            has_include: rules.iter().any(|rule| !rule.exclude),
+            mode,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exclude.is_empty()
+    }
+
+    // 文件是否应该保留
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.mode == RuleMode::All {
+            let mut matched = vec![false; self.exclude.len()];
+            for (target, set, indices) in &self.targets {
+                for index in set.matches(&target.text(path)).iter() {
+                    matched[indices[index]] = true;
+                }
+            }
+            return matched.iter().zip(&self.exclude).all(|(matched, exclude)| matched != exclude);
+        }
+        let last = self.targets.iter()
+            .filter_map(|(target, set, indices)| {
+                set.matches(&target.text(path)).iter().next_back().map(|matched| indices[matched])
+            })
+            .max();
+        match last {
+            Some(index) => !self.exclude[index],
+            None => !self.has_include,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keeps(rules: &[RegexRule], mode: RuleMode, path: &str) -> bool {
+        RuleSet::new(rules, mode).unwrap().matches(Path::new(path))
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let rules = [
+            RegexRule::include(r"\.rs$", RuleTarget::Name),
+            RegexRule::exclude("^tests?$", RuleTarget::Parent),
+            RegexRule::include("^keep_", RuleTarget::Name),
+        ];
+        assert!(keeps(&rules, RuleMode::Ordered, "/src/main.rs"));
+        assert!(!keeps(&rules, RuleMode::Ordered, "/src/tests/main.rs"));
+        assert!(keeps(&rules, RuleMode::Ordered, "/src/tests/keep_me.rs"));
+        // 没有规则匹配时，存在包含规则就排除
+        assert!(!keeps(&rules, RuleMode::Ordered, "/src/readme.md"));
+        // 规则顺序反过来时结果也反过来
+        let reversed = [rules[1].clone(), rules[0].clone()];
+        assert!(keeps(&reversed, RuleMode::Ordered, "/src/tests/main.rs"));
+    }
+
+    #[test]
+    fn only_exclude_rules_keep_unmatched_files() {
+        let rules = [RegexRule::exclude("^(tmp|bak)$", RuleTarget::Extension)];
+        assert!(keeps(&rules, RuleMode::Ordered, "/a/report.txt"));
+        assert!(!keeps(&rules, RuleMode::Ordered, "/a/report.bak"));
+        assert!(keeps(&rules, RuleMode::All, "/a/report.txt"));
+        assert!(!keeps(&rules, RuleMode::All, "/a/report.tmp"));
+    }
+
+    #[test]
+    fn all_mode_requires_every_rule() {
+        let rules = [
+            RegexRule::include("foo", RuleTarget::Name),
+            RegexRule::include("bar", RuleTarget::Name),
+            RegexRule::exclude("/old/", RuleTarget::Path),
+        ];
+        assert!(keeps(&rules, RuleMode::All, "/new/foo_bar.txt"));
+        assert!(!keeps(&rules, RuleMode::All, "/new/foo.txt"));
+        assert!(!keeps(&rules, RuleMode::All, "/old/foo_bar.txt"));
+        // 按顺序匹配时只要有一条包含规则最后匹配即可
+        assert!(keeps(&rules, RuleMode::Ordered, "/new/foo.txt"));
+    }
+
+    #[test]
+    fn case_insensitive_flag() {
+        let mut rules = [RegexRule::include("^readme", RuleTarget::Name)];
+        assert!(!keeps(&rules, RuleMode::Ordered, "/a/README.md"));
+        rules[0].case_insensitive = true;
+        assert!(keeps(&rules, RuleMode::Ordered, "/a/README.md"));
+        assert!(keeps(&rules, RuleMode::Ordered, "/a/readme.md"));
+    }
+
+    #[test]
+    fn each_target_matches_its_part_of_the_path() {
+        let path = "/home/user/docs/report.final.pdf";
+        let cases = [
+            (RuleTarget::Name, "^report\\.final\\.pdf$", "^docs$"),
+            (RuleTarget::Path, "^/home/user/docs/", "^report"),
+            (RuleTarget::Parent, "^docs$", "^user$"),
+            (RuleTarget::Extension, "^pdf$", "^final\\.pdf$"),
+        ];
+        for (target, matching, other) in cases {
+            assert!(keeps(&[RegexRule::include(matching, target)], RuleMode::Ordered, path), "{:?} {}", target, matching);
+            assert!(!keeps(&[RegexRule::include(other, target)], RuleMode::Ordered, path), "{:?} {}", target, other);
+        }
+        // 没有扩展名时扩展名为空
+        assert!(keeps(&[RegexRule::include("^$", RuleTarget::Extension)], RuleMode::Ordered, "/a/Makefile"));
+    }
+
+    #[test]
+    fn empty_and_invalid_patterns() {
+        let rules = [RegexRule::include("", RuleTarget::Name)];
+        assert!(RuleSet::new(&rules, RuleMode::Ordered).unwrap().is_empty());
+        assert!(keeps(&rules, RuleMode::Ordered, "/a/anything"));
+
+        let invalid = RegexRule::include("(", RuleTarget::Path);
+        assert!(invalid.validate().is_err());
+        assert!(RuleSet::new(&[invalid], RuleMode::Ordered).is_err());
+    }
+}
@@ -1,207 +1,286 @@
-pub const FILE_ATTRIBUTE_HIDDEN: u32 = 0x00000002;
-pub const FILE_ATTRIBUTE_READONLY: u32 = 0x00000001;
-pub const FILE_ATTRIBUTE_SYSTEM: u32 = 0x00000004;
-use std::time::{SystemTime, UNIX_EPOCH};
-use chrono::{NaiveDate, Datelike};
-
-pub static mut SEARCH_HIDDEN_FILES: bool = true; // Set to true if you want to search hidden files
-pub static mut SEARCH_HIDDEN_FOLDERS: bool = false; // Set to true if you want to search hidden folders
-pub static mut SEARCH_FILESIZE_MAXIMUM_LIMIT: u64 = 1024 * 1024 * 1024; // Set the maximum file size for searching
-pub static mut SEARCH_FILESIZE_MINIMUM_LIMIT: u64 = 0; // Set the minimum file size for searching
-pub static mut SEARCH_READONLY: bool = false; // Set to true if you want to search read-only files
-pub static mut SAVE_HASH: bool = false; // Set to true if you want to save file hashes
-pub static mut REGEX_CONTAIN_PATH: bool = false;// regex匹配项是否包含路径，即正则项是整个路径的正则项还是仅文件名的正则项
-pub static mut SEARCH_SYSTEM_FILES: bool = false; // Set to true if you want to search system files
-pub static mut SEARCH_TIMELIMIT: bool = false;
-pub static mut SEARCH_TIMELIMIT_CURRENTTIME_TYPE: bool = false;
-pub static mut SEARCH_COMPARE_WITH_CURRENT_TIME_LIMIT: u64 = 0;
-pub static mut SEARCH_COMPARE_WITH_CURRENT_TIME_NEWER: bool = true;
-pub static mut SEARCH_MODIFY_TIME_MAXIMUM_LIMIT: u64 = 1145141919810; // Set the maximum modification time for searching (0 means no limit)
-pub static mut SEARCH_MODIFY_TIME_MINIMUM_LIMIT: u64 = 0; // Set the minimum modification time for searching (0 means no limit)
-pub static mut SEARCH_DEPTH: u8 = 255; // Default search depth
-
-use std::io::Read;
-
-// 从config.json文件加载设置
-pub fn load_settings() -> (u8, String) {
-    let mut search_depth: u8 = 255; // Default search depth
-    let mut file_regex: String = String::from(r".*"); // Default regex
-    let open_file = std::fs::File::open("config.json");
-    match open_file {
-        Err(e) => {
-            eprintln!("Error opening config file: {}", e);
-            return (search_depth, file_regex);
-        },
-        Ok(mut file) => {
-            // Load settings from the file
-            let mut config_contents = String::new();
-            file.read_to_string(&mut config_contents).expect("Failed to read config file");
-            let json: serde_json::Value = serde_json::from_str(&config_contents).unwrap();
-            unsafe {
-                SEARCH_HIDDEN_FILES = json.get("search_hidden_files").unwrap().as_bool().unwrap();
-                SEARCH_HIDDEN_FOLDERS = json.get("search_hidden_folders").unwrap().as_bool().unwrap();
-                SEARCH_FILESIZE_MAXIMUM_LIMIT = json.get("search_filesize_maximum_limit").unwrap().as_u64().unwrap();
-                SEARCH_FILESIZE_MINIMUM_LIMIT = json.get("search_filesize_minimum_limit").unwrap().as_u64().unwrap();
-                SEARCH_READONLY = json.get("search_readonly").unwrap().as_bool().unwrap();
-                SAVE_HASH = json.get("save_hash").unwrap().as_bool().unwrap();
-                REGEX_CONTAIN_PATH = json.get("regex_contain_path").unwrap().as_bool().unwrap();
-                SEARCH_SYSTEM_FILES = json.get("search_system_files").unwrap().as_bool().unwrap();
-                SEARCH_TIMELIMIT = json.get("search_timelimit").unwrap().as_bool().unwrap();
-                SEARCH_TIMELIMIT_CURRENTTIME_TYPE = json.get("search_timelimit_currenttime_type").unwrap().as_bool().unwrap();
-                SEARCH_COMPARE_WITH_CURRENT_TIME_LIMIT = json.get("search_compare_with_current_time_limit").unwrap().as_u64().unwrap();
-                SEARCH_COMPARE_WITH_CURRENT_TIME_NEWER = json.get("search_compare_with_current_time_newer").unwrap().as_bool().unwrap();
-                SEARCH_MODIFY_TIME_MAXIMUM_LIMIT = json.get("search_modify_time_maximum_limit").unwrap().as_u64().unwrap();
-                SEARCH_MODIFY_TIME_MINIMUM_LIMIT = json.get("search_modify_time_minimum_limit").unwrap().as_u64().unwrap();
-                search_depth = json.get("search_depth").unwrap().as_u64().unwrap() as u8;
-                file_regex = json.get("file_regex").unwrap().as_str().unwrap().to_string();
-            }
+use super::rules::{RegexRule, RuleMode};
+use super::empty::default_junk_names;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Days, Local, Months, NaiveDate, NaiveDateTime, TimeZone};
+
+/// 日期限制比较的是哪一个时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeField {
+    #[default]
+    Modified, // 修改时间
+    Created, // 创建时间(部分文件系统不提供)
+    Accessed, // 最后访问时间
+}
+
+/// 搜索结果中包含哪些条目
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResultKind {
+    #[default]
+    Files, // 只有文件
+    Directories, // 只有目录，带有其中文件的总大小和数量
+    Both,
+}
+
+impl ResultKind {
+    // 查询语句中使用的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResultKind::Files => "files",
+            ResultKind::Directories => "dirs",
+            ResultKind::Both => "both",
         }
     }
-    (search_depth, file_regex)
-}
 
-// 保存设置到config.json文件
-pub fn save_settings(search_depth:u8 , file_regex: &String) {
-    let settings = serde_json::json!({
-        "search_hidden_files": unsafe { SEARCH_HIDDEN_FILES },
-        "search_hidden_folders": unsafe { SEARCH_HIDDEN_FOLDERS },
-        "search_filesize_maximum_limit": unsafe { SEARCH_FILESIZE_MAXIMUM_LIMIT },
-        "search_filesize_minimum_limit": unsafe { SEARCH_FILESIZE_MINIMUM_LIMIT },
-        "search_readonly": unsafe { SEARCH_READONLY },
-        "save_hash": unsafe { SAVE_HASH },
-        "regex_contain_path": unsafe { REGEX_CONTAIN_PATH },
-        "search_system_files": unsafe { SEARCH_SYSTEM_FILES },
-        "search_timelimit": unsafe { SEARCH_TIMELIMIT },
-        "search_timelimit_currenttime_type": unsafe { SEARCH_TIMELIMIT_CURRENTTIME_TYPE },
-        "search_compare_with_current_time_limit": unsafe { SEARCH_COMPARE_WITH_CURRENT_TIME_LIMIT },
-        "search_compare_with_current_time_newer": unsafe { SEARCH_COMPARE_WITH_CURRENT_TIME_NEWER },
-        "search_modify_time_maximum_limit": unsafe { SEARCH_MODIFY_TIME_MAXIMUM_LIMIT },
-        "search_modify_time_minimum_limit": unsafe { SEARCH_MODIFY_TIME_MINIMUM_LIMIT },
-        "search_depth": search_depth,
-        "file_regex": file_regex
-    });
+    pub fn includes_files(&self) -> bool {
+        *self != ResultKind::Directories
+    }
 
-    let json_string = serde_json::to_string(&settings).unwrap();
-    std::fs::write("config.json", json_string).expect("Unable to write config file");
+    pub fn includes_directories(&self) -> bool {
+        *self != ResultKind::Files
+    }
 }
 
-// 下面的函数用于其他程序调用以修改参数
-// 对深度和正则表达式的修改方式是直接修改传入search的变量
+/// 遇到符号链接时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkMode {
+    Skip, // 忽略符号链接
+    List, // 把符号链接本身作为结果列出，不进入链接指向的目录
+    #[default]
+    Follow, // 跟随符号链接，通过设备号+inode检测循环，同一目录只搜索一次
+}
 
-// 设置是否搜索隐藏文件
-pub fn modify_search_hidden_files(value: bool) {
-    unsafe {
-        SEARCH_HIDDEN_FILES = value;
+impl SymlinkMode {
+    // 查询语句中使用的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SymlinkMode::Skip => "skip",
+            SymlinkMode::List => "list",
+            SymlinkMode::Follow => "follow",
+        }
     }
-}
 
-// 设置是否搜索隐藏文件夹
-pub fn modify_search_hidden_folders(value: bool) {
-    unsafe {
-        SEARCH_HIDDEN_FOLDERS = value;
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(SymlinkMode::Skip),
+            "list" => Some(SymlinkMode::List),
+            "follow" => Some(SymlinkMode::Follow),
+            _ => None,
+        }
     }
 }
 
-// 设置搜索文件大小的最大限制
-pub fn modify_search_filesize_maximum_limit(value: u64) {
-    unsafe {
-        SEARCH_FILESIZE_MAXIMUM_LIMIT = value;
-    }
+/// 记录哈希值和查找重复文件时使用的哈希算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Xxh3, // 128位的XXH3，不是加密哈希，但速度快得多，适合查找重复文件
+    Sha1,
+    #[default]
+    Sha256,
+    Sha512,
+    Md5,
 }
 
-// 设置搜索文件大小的最小限制
-pub fn modify_search_filesize_minimum_limit(value: u64) {
-    unsafe {
-        SEARCH_FILESIZE_MINIMUM_LIMIT = value;
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 5] = [
+        HashAlgorithm::Xxh3,
+        HashAlgorithm::Sha1,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Md5,
+    ];
+
+    // 查询语句和校验文件名中使用的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Xxh3 => "xxh3",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Md5 => "md5",
+        }
     }
-}
 
-// 设置是否搜索只读文件
-pub fn modify_search_readonly(value: bool) {
-    unsafe {
-        SEARCH_READONLY = value;
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "xxh3" => Some(HashAlgorithm::Xxh3),
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "sha512" => Some(HashAlgorithm::Sha512),
+            "md5" => Some(HashAlgorithm::Md5),
+            _ => None,
+        }
     }
 }
 
-// 设置是否保存文件哈希，用于去重
-pub fn modify_save_hash(value: bool) {
-    unsafe {
-        SAVE_HASH = value;
-    }
+/// 单次搜索的全部参数
+/// 每次搜索持有自己的一份，因此多个搜索可以在不同线程中以不同的过滤条件同时进行
+/// 对单个文件的过滤设置通过Predicate::from_options转换为条件树；搜索时只用其中的目录和扩展名设置剪枝，见get_files_streaming
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    pub search_hidden_files: bool, // Set to true if you want to search hidden files
+    pub search_hidden_folders: bool, // Set to true if you want to search hidden folders
+    pub search_filesize_maximum_limit: u64, // Set the maximum file size for searching
+    pub search_filesize_minimum_limit: u64, // Set the minimum file size for searching
+    pub search_readonly: bool, // Set to true if you want to search read-only files
+    pub save_hash: bool, // Set to true if you want to save file hashes
+    pub hash_algorithm: HashAlgorithm, // 记录哈希值时使用的算法
+    pub regex_contain_path: bool, // regex匹配项是否包含路径，即正则项是整个路径的正则项还是仅文件名的正则项
+    pub search_system_files: bool, // Set to true if you want to search system files
+    pub search_timelimit: bool,
+    pub search_timelimit_currenttime_type: bool,
+    pub search_compare_with_current_time_limit: u64,
+    pub search_compare_with_current_time_newer: bool,
+    pub search_modify_time_maximum_limit: u64, // Set the maximum modification time for searching (0 means no limit)
+    pub search_modify_time_minimum_limit: u64, // Set the minimum modification time for searching (0 means no limit)
+    pub search_time_field: TimeField, // 时间限制作用于修改/创建/访问时间
+    pub worker_threads: usize, // 遍历目录使用的工作线程数量，默认为CPU核心数
+    pub symlink_mode: SymlinkMode, // 符号链接的处理方式
+    pub search_content: bool, // 正则表达式匹配文件内容而不是文件名/路径
+    pub regex_invert: bool, // 只保留不匹配正则表达式的文件
+    pub regex_rules: Vec<RegexRule>, // 按顺序排列的正则表达式规则，与file_regex同时生效
+    pub rule_mode: RuleMode, // 正则规则按顺序匹配还是全部匹配
+    pub content_scan_limit: u64, // 搜索文件内容时每个文件最多读取的字节数
+    pub include_globs: Vec<String>, // 包含的通配符，为空时不限制
+    pub exclude_globs: Vec<String>, // 排除的通配符
+    pub excluded_directories: Vec<String>, // 不进入的目录，可以是目录名(如target)或路径
+    pub respect_ignore_files: bool, // 是否遵循各层目录中的.gitignore/.ignore/.esignore
+    pub include_extensions: Vec<String>, // 扩展名白名单，为空时不限制
+    pub exclude_extensions: Vec<String>, // 扩展名黑名单
+    pub result_kind: ResultKind, // 结果中包含文件、目录还是两者
+    pub find_empty: bool, // 只查找空文件(长度为0)和空目录(各层子目录中都没有文件)
+    pub junk_as_empty: bool, // 查找空目录时把junk_names中的文件视为不存在
+    pub junk_names: Vec<String>, // 系统自动生成的文件，如Thumbs.db、.DS_Store
 }
 
-// 设置正则表达式匹配的是文件名还是文件路径
-pub fn modify_regex_contain_path(value: bool) {
-    unsafe {
-        REGEX_CONTAIN_PATH = value;
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            search_hidden_files: true,
+            search_hidden_folders: false,
+            search_filesize_maximum_limit: u64::MAX, // 不限制
+            search_filesize_minimum_limit: 0,
+            search_readonly: false,
+            save_hash: false,
+            hash_algorithm: HashAlgorithm::default(),
+            regex_contain_path: false,
+            search_system_files: false,
+            search_timelimit: false,
+            search_timelimit_currenttime_type: false,
+            search_compare_with_current_time_limit: 0,
+            search_compare_with_current_time_newer: true,
+            search_modify_time_maximum_limit: 1145141919810,
+            search_modify_time_minimum_limit: 0,
+            search_time_field: TimeField::default(),
+            worker_threads: default_worker_threads(),
+            symlink_mode: SymlinkMode::default(),
+            search_content: false,
+            regex_invert: false,
+            regex_rules: Vec::new(),
+            rule_mode: RuleMode::default(),
+            content_scan_limit: 16 * 1024 * 1024,
+            include_globs: Vec::new(),
+            exclude_globs: Vec::new(),
+            excluded_directories: Vec::new(),
+            respect_ignore_files: false,
+            include_extensions: Vec::new(),
+            exclude_extensions: Vec::new(),
+            result_kind: ResultKind::default(),
+            find_empty: false,
+            junk_as_empty: false,
+            junk_names: default_junk_names(),
+        }
     }
 }
 
-// 设置是否搜索系统文件
-pub fn modify_search_system_files(value: bool) {
-    unsafe {
-        SEARCH_SYSTEM_FILES = value;
-    }
+// 默认的工作线程数量: CPU核心数，获取失败时使用4
+pub fn default_worker_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(4)
 }
 
-// 设置时间限制
+// 下面的函数用于设置时间限制
+// 对深度和正则表达式的修改方式是直接修改传入search的变量
 // 时间限制有两种:第一种是当前时间到现在的时间限制，第二种是修改时间的最大最小限制
+impl SearchOptions {
+    // 取消时间限制
+    pub fn cancel_file_timelimit(&mut self) {
+        self.search_timelimit = false;
+    }
+
+    // 传入当前时间到现在的时间限制 数字 + 单位 + 修改日期比限制时间更新/更旧
+    // 天/周/月/年按本地日历计算，例如"1个月以内"指上个月的同一天同一时刻之后，而不是固定的30天
+    pub fn add_type1_timelimit(&mut self, num : u64, unit: &str,newer: bool) {
+        let now = Local::now();
+        let cutoff = calendar_cutoff(now, num, unit);
+        // 无法表示的时间(如一万年前)视为不限制起点
+        let to_now_limit: u64 = match cutoff {
+            Some(cutoff) => (now - cutoff).num_seconds().max(0) as u64,
+            None => now.timestamp().max(0) as u64,
+        };
+        self.search_timelimit = true;
+        self.search_timelimit_currenttime_type = true;
+        self.search_compare_with_current_time_limit = to_now_limit;
+        self.search_compare_with_current_time_newer = newer;
+    }
+
+    // 传入修改日期 下限的年月日 + 上限的年月日
+    // 日期按本地时区解释: 从下限当天的0:00:00到上限当天的23:59:59
+    pub fn add_type2_timelimit(&mut self, minimum_year: i32, minimum_month: u32, minimum_day: u32, maximum_year: i32, maximum_month: u32, maximum_day: u32) {
+        let minimum_date = NaiveDate::from_ymd_opt(minimum_year, minimum_month, minimum_day);
+        let maximum_date = NaiveDate::from_ymd_opt(maximum_year, maximum_month, maximum_day);
 
-// 取消时间限制
-pub fn cancel_file_timelimit() {
-    unsafe {
-        SEARCH_TIMELIMIT = false;
+        if let (Some(min_date), Some(max_date)) = (minimum_date, maximum_date) {
+            let minimum_timestamp = local_timestamp(min_date.and_hms_opt(0, 0, 0).unwrap(), true);
+            let maximum_timestamp = local_timestamp(max_date.and_hms_opt(23, 59, 59).unwrap(), false);
+
+            self.search_timelimit = true;
+            self.search_timelimit_currenttime_type = false;
+            self.search_modify_time_minimum_limit = minimum_timestamp;
+            self.search_modify_time_maximum_limit = maximum_timestamp;
+        } else {
+            eprintln!("无效的日期输入: 最小日期({}, {}, {}), 最大日期({}, {}, {})",
+                minimum_year, minimum_month, minimum_day,
+                maximum_year, maximum_month, maximum_day);
+        }
     }
 }
 
-// 传入当前时间到现在的时间限制 数字 + 单位 + 修改日期比限制时间更新/更旧
-pub fn add_type1_timelimit(num : u64, unit: &str,newer: bool) {
-    let to_now_limit: u64 = match unit {
-        "second" => num,
-        "minute" => num * 60,
-        "hour" => num * 3600,
-        "day" => num * 86400,
-        "week" => num * 604800,
-        "month" => num * 2592000,
-        "year" => num * 31536000,
-        _ => 0,
+// 从now往前推num个单位的时刻，超出可表示范围时返回None
+// 天和周按日历天数计算，夏令时切换的那天也是"同一时刻"；月和年在目标月份没有这一天时取该月最后一天
+fn calendar_cutoff(now: DateTime<Local>, num: u64, unit: &str) -> Option<DateTime<Local>> {
+    let seconds = |factor: u64| {
+        let seconds = i64::try_from(num.checked_mul(factor)?).ok()?;
+        now.checked_sub_signed(chrono::TimeDelta::try_seconds(seconds)?)
     };
-    unsafe {
-        SEARCH_TIMELIMIT = true;
-        SEARCH_TIMELIMIT_CURRENTTIME_TYPE = true;
-        SEARCH_COMPARE_WITH_CURRENT_TIME_LIMIT = to_now_limit;
-        SEARCH_COMPARE_WITH_CURRENT_TIME_NEWER = newer;
+    let months = |factor: u64| {
+        let months = u32::try_from(num.checked_mul(factor)?).ok()?;
+        now.checked_sub_months(Months::new(months))
+    };
+    match unit {
+        "second" => seconds(1),
+        "minute" => seconds(60),
+        "hour" => seconds(3600),
+        "day" => now.checked_sub_days(Days::new(num)),
+        "week" => now.checked_sub_days(Days::new(num.checked_mul(7)?)),
+        "month" => months(1),
+        "year" => months(12),
+        _ => Some(now),
     }
 }
 
-// 传入修改日期 下限的年月日 + 上限的年月日
-pub fn add_type2_timelimit(minimum_year: i32, minimum_month: u32, minimum_day: u32, maximum_year: i32, maximum_month: u32, maximum_day: u32) {
-    println!("设置日期范围限制: 从 {}/{}/{} 到 {}/{}/{}", 
-        minimum_year, minimum_month, minimum_day, 
-        maximum_year, maximum_month, maximum_day);
-    
-    let minimum_date = NaiveDate::from_ymd_opt(minimum_year, minimum_month, minimum_day);
-    let maximum_date = NaiveDate::from_ymd_opt(maximum_year, maximum_month, maximum_day);
-
-    if let (Some(min_date), Some(max_date)) = (minimum_date, maximum_date) {
-        
-        let minimum_timestamp = min_date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() as u64;
-        let maximum_timestamp = max_date.and_hms_opt(23, 59, 59).unwrap().and_utc().timestamp() as u64;
-        
-        println!("转换为时间戳: 最小值={}, 最大值={}", minimum_timestamp, maximum_timestamp);
-        
-        unsafe {
-            SEARCH_TIMELIMIT = true;
-            SEARCH_TIMELIMIT_CURRENTTIME_TYPE = false;
-            SEARCH_MODIFY_TIME_MINIMUM_LIMIT = minimum_timestamp;
-            SEARCH_MODIFY_TIME_MAXIMUM_LIMIT = maximum_timestamp;
-            
-            // println!("日期限制参数设置完成: SEARCH_TIMELIMIT={}, SEARCH_TIMELIMIT_CURRENTTIME_TYPE={}, 最小时间={}, 最大时间={}", 
-            //     SEARCH_TIMELIMIT, SEARCH_TIMELIMIT_CURRENTTIME_TYPE, SEARCH_MODIFY_TIME_MINIMUM_LIMIT, SEARCH_MODIFY_TIME_MAXIMUM_LIMIT);
-        }
-    } else {
-        eprintln!("无效的日期输入: 最小日期({}, {}, {}), 最大日期({}, {}, {})",
-            minimum_year, minimum_month, minimum_day,
-            maximum_year, maximum_month, maximum_day);
+// 把本地时间转换为Unix时间戳
+// 夏令时回拨时同一本地时间出现两次，下限取较早的一个、上限取较晚的一个，保证整天都被包含
+// 夏令时跳过的本地时间不存在，此时往后顺延一小时
+fn local_timestamp(local: NaiveDateTime, earliest: bool) -> u64 {
+    let resolved = Local.from_local_datetime(&local);
+    let time = if earliest { resolved.earliest() } else { resolved.latest() };
+    let time = time.or_else(|| {
+        Local.from_local_datetime(&(local + chrono::TimeDelta::hours(1))).earliest()
+    });
+    match time {
+        Some(time) => time.timestamp().max(0) as u64,
+        None => local.and_utc().timestamp().max(0) as u64,
     }
-}
\ No newline at end of file
+}
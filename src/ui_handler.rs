@@ -1,22 +1,43 @@
 use slint::{Model, VecModel, SharedString};
 use std::rc::Rc;
+use chrono::TimeZone;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
 use native_dialog::{FileDialog, MessageDialog, MessageType};
-use crate::filter::SearchFilter;
-use crate::search_file::SingleFileInformations;
+use crate::filter::{format_size_range, FileCategory, SearchFilter};
+use crate::search_file::file_types::parse_extensions;
+use crate::search_file::{CancelToken, SearchProgress, SearchRoot, SingleFileInformations};
+use crate::search_file::settings::{HashAlgorithm, ResultKind, SymlinkMode, TimeField};
+use crate::search_file::hash_cache::HashCache;
+use crate::search_file::checksum::ManifestImport;
+use crate::search_file::duplicates::{DuplicateActionProgress, DuplicateActionReport, DuplicateGroup, DuplicateProgress, KeeperPolicy};
+use crate::search_file::rules::{RegexRule, RuleMode, RuleTarget};
 use crate::helper::SearchHelper;
+use crate::query::{parse_query, to_query};
 
-slint::include_modules!();
+// slint生成的代码中包含界面没有用到的类型(如标准控件中的TextStyle)
+#[allow(dead_code)]
+mod ui {
+    slint::include_modules!();
+}
+use ui::*;
 
 // 搜索结果模型
+// UI中的FileInfo只用于显示，完整的搜索结果保存在files中，FileInfo.id是结果在files中的下标
+// 复制、移动、查找重复文件等操作都从files中取出结果，不从UI中的字段还原(如超出int范围的文件大小)
 pub struct SearchResultModel {
     inner: Rc<VecModel<FileInfo>>,
+    files: Arc<Mutex<Vec<SingleFileInformations>>>,
 }
 
 impl SearchResultModel {
     fn new() -> Self {
         Self {
             inner: Rc::new(VecModel::default()),
+            files: Arc::new(Mutex::new(Vec::new())),
         }
     }
     
@@ -25,38 +46,295 @@ impl SearchResultModel {
         while self.inner.row_count() > 0 {
             self.inner.remove(0);
         }
+        self.files.lock().unwrap().clear();
+    }
+}
+
+// 搜索结果每批最多攒多少条再交给UI线程
+const RESULT_BATCH_SIZE: usize = 500;
+// 搜索进度和结果的刷新间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// 将搜索结果转换为UI中显示的文件信息，id是结果在SearchResultModel::files中的下标
+fn to_file_info(file: &SingleFileInformations, id: usize) -> FileInfo {
+    FileInfo {
+        id: id as i32,
+        path: file.path.to_string_lossy().to_string().into(),
+        name: file.name.clone().into(),
+        size_text: format_bytes(file.size).into(),
+        time_text: format_time(file.time).into(),
+        created_text: file.created.map(format_time).unwrap_or_default().into(),
+        accessed_text: file.accessed.map(format_time).unwrap_or_default().into(),
+        hash: file.hash.clone().into(),
+        hash_algorithm: file.hash_algorithm.as_str().into(),
+        link_target: file.link_target.as_ref().map(|target| target.to_string_lossy().to_string()).unwrap_or_default().into(),
+        match_line: file.matches.first().map(|m| m.line as i32).unwrap_or(0),
+        match_text: file.matches.first().map(|m| m.snippet.clone()).unwrap_or_default().into(),
+        is_dir: file.is_dir,
+        file_count_text: file.file_count.to_string().into(),
+        selected: false, // 默认不选中
+    }
+}
+
+// 取出UI中一行对应的完整搜索结果
+fn stored_file(files: &Mutex<Vec<SingleFileInformations>>, info: &FileInfo) -> SingleFileInformations {
+    files.lock().unwrap()[info.id as usize].clone()
+}
+
+// 把新的结果加入files，返回对应的UI行
+fn store_files(files: &Mutex<Vec<SingleFileInformations>>, new_files: Vec<SingleFileInformations>) -> Vec<FileInfo> {
+    let mut files = files.lock().unwrap();
+    let start = files.len();
+    let rows = new_files.iter().enumerate().map(|(offset, file)| to_file_info(file, start + offset)).collect();
+    files.extend(new_files);
+    rows
+}
+
+// 用新的结果替换之前的全部结果
+fn replace_results(search_results: &VecModel<FileInfo>, files: &Mutex<Vec<SingleFileInformations>>, new_files: Vec<SingleFileInformations>) {
+    files.lock().unwrap().clear();
+    search_results.set_vec(store_files(files, new_files));
+}
+
+// 把界面中的目录项转换为搜索根目录，深度为0表示不限制
+fn search_root(item: &DirectoryItem) -> SearchRoot {
+    let max_depth = if item.max_depth <= 0 { usize::MAX } else { item.max_depth as usize };
+    SearchRoot::with_depth(PathBuf::from(item.path.as_str()), item.min_depth.max(0) as usize, max_depth)
+}
+
+// 从搜索线程把一批结果追加到UI的搜索结果中
+// UI中的search_results绑定的就是SearchResultModel中的VecModel，因此追加后界面会自动刷新
+fn append_results_in_event_loop(ui_weak: &slint::Weak<AppWindow>, rows: Vec<FileInfo>) {
+    if rows.is_empty() {
+        return;
+    }
+    let ui_weak = ui_weak.clone();
+    let _ = slint::invoke_from_event_loop(move || {
+        if let Some(ui) = ui_weak.upgrade() {
+            let results = ui.get_search_results();
+            if let Some(model) = results.as_any().downcast_ref::<VecModel<FileInfo>>() {
+                model.extend(rows);
+            }
+        }
+    });
+}
+
+// 哈希值缓存文件的位置说明
+fn hash_cache_location_text() -> String {
+    match HashCache::default_location() {
+        Some(location) => format!("缓存位置: {}", location.display()),
+        None => "无法确定数据目录，哈希值不会保存到磁盘上".to_string(),
+    }
+}
+
+// 界面中选择的保留规则
+fn keeper_policy(index: i32, preferred_folder: &str) -> KeeperPolicy {
+    match index {
+        0 => KeeperPolicy::Oldest,
+        1 => KeeperPolicy::Newest,
+        2 => KeeperPolicy::ShortestPath,
+        3 => KeeperPolicy::PreferredFolder(PathBuf::from(preferred_folder.trim())),
+        _ => KeeperPolicy::Manual,
     }
-      // 添加搜索结果
-    fn add_results(&self, results: &[SingleFileInformations]) {
-        for file in results {
-            let file_info = FileInfo {
+}
+
+// 显示用的文件大小，保留一位小数
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+// 把Unix时间戳(秒)格式化为本地时间，超出范围时为空
+fn format_time(seconds: u64) -> String {
+    i64::try_from(seconds).ok()
+        .and_then(|seconds| chrono::Local.timestamp_opt(seconds, 0).single())
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+// 重新生成重复文件视图中的数据
+fn refresh_duplicate_view(ui: &AppWindow, groups: &[DuplicateGroup]) {
+    let rows: Vec<DuplicateGroupData> = groups.iter()
+        .map(|group| DuplicateGroupData {
+            hash: group.hash.chars().take(16).collect::<String>().into(),
+            size_text: format_bytes(group.size).into(),
+            reclaimable_text: format_bytes(group.reclaimable()).into(),
+            files: Rc::new(VecModel::from(group.files.iter().zip(&group.keep).map(|(file, keep)| DuplicateFileData {
                 path: file.path.to_string_lossy().to_string().into(),
-                name: file.name.clone().into(),
-                size: file.size as i32,
-                time: file.time as i32,
-                hash: file.hash.clone().into(),
-                selected: false, // 默认不选中
-            };
-            self.inner.push(file_info);
+                time_text: format_time(file.time).into(),
+                keep: *keep,
+            }).collect::<Vec<_>>())).into(),
+        })
+        .collect();
+    let copies: usize = groups.iter().map(|group| group.duplicates().count()).sum();
+    let reclaimable: u64 = groups.iter().filter(|group| group.has_keeper()).map(|group| group.reclaimable()).sum();
+    let unkept = groups.iter().filter(|group| !group.has_keeper()).count();
+    let mut summary = format!("共 {} 组重复文件，{} 个未保留的副本，可释放 {}", groups.len(), copies, format_bytes(reclaimable));
+    if unkept > 0 {
+        summary.push_str(&format!("；{} 组没有保留任何文件，不会被处理", unkept));
+    }
+    ui.set_duplicate_groups(Rc::new(VecModel::from(rows)).into());
+    ui.set_duplicate_summary(summary.into());
+}
+
+// 处理重复文件之后，从各组中去掉已经处理的文件，只剩一个文件的组不再显示
+// 文件被移走时(remove_from_results)也从搜索结果中去掉；替换为链接的文件仍在原位置，保留在搜索结果中
+fn remove_processed_duplicates(ui: &AppWindow, groups: &mut Vec<DuplicateGroup>, search_results: &VecModel<FileInfo>, report: &DuplicateActionReport, remove_from_results: bool) {
+    let processed: std::collections::HashSet<&PathBuf> = report.processed.iter().collect();
+    for group in groups.iter_mut() {
+        let keep = std::mem::take(&mut group.keep);
+        let (files, keep): (Vec<_>, Vec<_>) = std::mem::take(&mut group.files).into_iter()
+            .zip(keep)
+            .filter(|(file, _)| !processed.contains(&file.path))
+            .unzip();
+        group.files = files;
+        group.keep = keep;
+    }
+    groups.retain(|group| group.files.len() >= 2);
+    
+    if remove_from_results {
+        let remaining: Vec<FileInfo> = search_results.iter()
+            .filter(|file_info| !processed.contains(&PathBuf::from(file_info.path.as_str())))
+            .collect();
+        search_results.set_vec(remaining);
+        ui.set_selected_count(search_results.iter().filter(|file_info| file_info.selected).count() as i32);
+    }
+    
+    refresh_duplicate_view(ui, groups);
+    if groups.is_empty() {
+        ui.set_show_duplicates(false);
+    }
+}
+
+// 处理重复文件的结果说明
+fn format_duplicate_report(action: &str, report: &DuplicateActionReport) -> String {
+    let mut text = format!("已{} {} 个文件，释放 {}", action, report.processed.len(), format_bytes(report.reclaimed));
+    if report.symlinks > 0 {
+        text.push_str(&format!("\n其中 {} 个文件与保留的文件不在同一文件系统中，使用了符号链接", report.symlinks));
+    }
+    if report.skipped_groups > 0 {
+        text.push_str(&format!("\n{} 组没有保留任何文件，未处理", report.skipped_groups));
+    }
+    if report.cancelled {
+        text.push_str("\n已停止，其余文件未处理");
+    }
+    text.push_str(&format_errors(&report.errors));
+    text
+}
+
+// 处理失败的文件列表，最多列出10个；没有失败时为空
+fn format_errors(errors: &[(PathBuf, String)]) -> String {
+    let mut text = String::new();
+    if !errors.is_empty() {
+        text.push_str(&format!("\n{} 个文件处理失败:", errors.len()));
+        for (path, error) in errors.iter().take(10) {
+            text.push_str(&format!("\n{}: {}", path.display(), error));
         }
     }
+    text
+}
+
+// 在后台线程中用run处理未保留的重复文件，期间在状态栏显示进度，可以用停止按钮取消
+// 处理的是确认时各组的副本；结束后回到UI线程，从重复文件视图(remove_from_results时也从搜索结果)中去掉已处理的文件并显示结果
+fn start_duplicate_action<F>(ui: &AppWindow, duplicate_groups: &Arc<Mutex<Vec<DuplicateGroup>>>, current_cancel: &Rc<std::cell::RefCell<Option<CancelToken>>>, progress_timer: &Rc<slint::Timer>, action: &'static str, remove_from_results: bool, run: F)
+where
+    F: FnOnce(&[DuplicateGroup], &DuplicateActionProgress, &CancelToken) -> DuplicateActionReport + Send + 'static,
+{
+    let groups = duplicate_groups.lock().unwrap().clone();
+    ui.set_search_running(true);
+    ui.set_search_status(format!("正在{}...", action).into());
+    let cancel = CancelToken::new();
+    *current_cancel.borrow_mut() = Some(cancel.clone());
     
-    // 获取所有搜索结果
-    fn get_all(&self) -> Vec<SingleFileInformations> {
-        let mut results = Vec::new();
-        for i in 0..self.inner.row_count() {
-            if let Some(info) = self.inner.row_data(i) {
-                results.push(SingleFileInformations {
-                    path: PathBuf::from(info.path.as_str()),
-                    name: info.name.as_str().to_string(),
-                    size: info.size as u64,
-                    time: info.time as u64,
-                    hash: info.hash.as_str().to_string(),
-                });
+    let progress = Arc::new(DuplicateActionProgress::default());
+    let timer_weak = Rc::downgrade(progress_timer);
+    let timer_ui = ui.as_weak();
+    let timer_progress = progress.clone();
+    progress_timer.start(slint::TimerMode::Repeated, PROGRESS_INTERVAL, move || {
+        let Some(ui) = timer_ui.upgrade() else { return };
+        if !ui.get_search_running() {
+            if let Some(timer) = timer_weak.upgrade() {
+                timer.stop();
             }
+            return;
         }
-        results
+        ui.set_search_status(format!(
+            "正在{}: {}/{} 个文件",
+            action,
+            timer_progress.done.load(Ordering::Relaxed),
+            timer_progress.total.load(Ordering::Relaxed)
+        ).into());
+    });
+    
+    let ui_weak = ui.as_weak();
+    let duplicate_groups = duplicate_groups.clone();
+    thread::spawn(move || {
+        let report = run(&groups, &progress, &cancel);
+        let _ = slint::invoke_from_event_loop(move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            ui.set_search_running(false);
+            ui.set_search_status(format!("{}{}: {} 个文件", action, if report.cancelled { "已停止" } else { "完成" }, report.processed.len()).into());
+            {
+                let mut groups = duplicate_groups.lock().unwrap();
+                let results = ui.get_search_results();
+                if let Some(model) = results.as_any().downcast_ref::<VecModel<FileInfo>>() {
+                    remove_processed_duplicates(&ui, &mut groups, model, &report, remove_from_results);
+                }
+            }
+            MessageDialog::new()
+                .set_type(if report.errors.is_empty() { MessageType::Info } else { MessageType::Warning })
+                .set_title("处理完成")
+                .set_text(&format_duplicate_report(action, &report))
+                .show_alert()
+                .unwrap();
+        });
+    });
+}
+
+// 重复文件查找进度的显示文本
+fn format_duplicate_progress(progress: &DuplicateProgress) -> String {
+    format!(
+        "正在查找重复文件: {} 个文件大小相同，已比较 {} 个文件的首尾，{} 个文件的完整内容",
+        progress.candidates.load(Ordering::Relaxed),
+        progress.partial_hashed.load(Ordering::Relaxed),
+        progress.full_hashed.load(Ordering::Relaxed)
+    )
+}
+
+// 导入校验文件后的状态文本
+fn format_checksum_import(import: &ManifestImport) -> String {
+    let mut text = format!(
+        "校验完成: {} 个文件一致，{} 个文件内容不一致，{} 个文件不存在",
+        import.matched,
+        import.mismatched.len(),
+        import.missing.len()
+    );
+    if import.malformed > 0 {
+        text.push_str(&format!("，{} 行无法解析", import.malformed));
     }
+    if import.report.cancelled {
+        text.push_str("(已停止，只校验了一部分文件)");
+    }
+    text
+}
+
+// 搜索进度的显示文本
+fn format_search_progress(progress: &SearchProgress, elapsed: Duration) -> String {
+    format!(
+        "已扫描 {} 个目录，匹配 {} 个文件，用时 {:.1} 秒",
+        progress.directories_scanned.load(Ordering::Relaxed),
+        progress.files_matched.load(Ordering::Relaxed),
+        elapsed.as_secs_f64()
+    )
 }
 
 /// UI交互处理器
@@ -66,6 +344,9 @@ pub struct UIHandler {
     pub current_filter: Rc<std::cell::RefCell<SearchFilter>>,
     pub search_results: SearchResultModel,
     pub selected_paths: Rc<VecModel<SharedString>>,
+    pub duplicate_groups: Arc<Mutex<Vec<DuplicateGroup>>>, // 重复文件视图中的各组，查找在后台线程中进行
+    pub current_cancel: Rc<std::cell::RefCell<Option<CancelToken>>>, // 当前正在进行的后台任务的取消标记，供停止按钮使用
+    pub progress_timer: Rc<slint::Timer>, // 定时刷新后台任务的进度
 }
 
 impl UIHandler {    /// 创建新的UI处理器
@@ -84,6 +365,9 @@ impl UIHandler {    /// 创建新的UI处理器
             current_filter,
             search_results: SearchResultModel::new(),
             selected_paths: Rc::new(VecModel::default()),
+            duplicate_groups: Arc::new(Mutex::new(Vec::new())),
+            current_cancel: Rc::new(std::cell::RefCell::new(None)),
+            progress_timer: Rc::new(slint::Timer::default()),
         };
         
         // 设置初始过滤器数据到UI
@@ -96,15 +380,8 @@ impl UIHandler {    /// 创建新的UI处理器
         self.setup_directory_callbacks();
         self.setup_filter_callbacks();
         self.setup_search_callbacks();
-    }
-      /// 获取当前的过滤设置
-    pub fn get_current_filter(&self) -> std::cell::Ref<SearchFilter> {
-        self.current_filter.borrow()
-    }
-    
-    /// 获取当前过滤设置的副本
-    pub fn get_current_filter_clone(&self) -> SearchFilter {
-        self.current_filter.borrow().clone()
+        self.setup_duplicate_callbacks();
+        self.setup_hash_cache_callbacks();
     }
     
     /// 设置目录相关的回调
@@ -135,6 +412,16 @@ impl UIHandler {    /// 创建新的UI处理器
                 Self::toggle_directory_selection(&directories_clone, &ui, index);
             }
         });
+        
+        // 目录搜索深度修改回调
+        let directories_clone = self.directories.clone();
+        self.ui.on_directory_depth_changed(move |index, min_depth, max_depth| {
+            if let Some(mut item) = directories_clone.row_data(index as usize) {
+                item.min_depth = min_depth.max(0);
+                item.max_depth = max_depth.max(0);
+                directories_clone.set_row_data(index as usize, item);
+            }
+        });
     }    /// 添加目录
     fn add_directory(directories: &Rc<VecModel<DirectoryItem>>, ui: &AppWindow) {
         // 打开文件夹选择对话框
@@ -147,47 +434,48 @@ impl UIHandler {    /// 创建新的UI处理器
                 let mut already_exists = false;
                 
                 for i in 0..directories.row_count() {
-                    if let Some(item) = directories.row_data(i) {
-                        if item.path.as_str() == path_str {
-                            already_exists = true;
-                            break;
-                        }
+                    if let Some(item) = directories.row_data(i)
+                        && item.path.as_str() == path_str {
+                        already_exists = true;
+                        break;
                     }
                 }
                 
-                if already_exists {
-                    println!("目录已存在: {}", path_str);
-                } else {
+                if !already_exists {
                     let new_dir = DirectoryItem {
                         path: path_str.clone().into(),
                         selected: false,
+                        min_depth: 0,
+                        max_depth: 0,
                     };
                     
                     directories.push(new_dir);
                     // 强制更新UI
                     ui.set_directories(directories.clone().into());
-                    println!("添加了新目录: {}", path_str);
                 }
             }
             Ok(None) => {
-                println!("用户取消了目录选择");
+                // 用户取消了选择
             }
             Err(e) => {
-                println!("打开文件对话框时出错: {}", e);
+                MessageDialog::new()
+                    .set_type(MessageType::Error)
+                    .set_title("错误")
+                    .set_text(&format!("文件夹选择对话框错误: {}", e))
+                    .show_alert()
+                    .unwrap();
             }
         }
     }
     
     /// 删除选中的目录
     fn remove_selected_directories(directories: &Rc<VecModel<DirectoryItem>>, ui: &AppWindow) {
-        let mut removed_count = 0;
         let mut i = 0;
         
         while i < directories.row_count() {
             if let Some(item) = directories.row_data(i) {
                 if item.selected {
                     directories.remove(i);
-                    removed_count += 1;
                 } else {
                     i += 1;
                 }
@@ -198,7 +486,6 @@ impl UIHandler {    /// 创建新的UI处理器
         
         // 强制更新UI
         ui.set_directories(directories.clone().into());
-        println!("删除了 {} 个目录", removed_count);
     }
     
     /// 切换目录的选择状态
@@ -209,52 +496,14 @@ impl UIHandler {    /// 创建新的UI处理器
             
             // 强制更新UI
             ui.set_directories(directories.clone().into());
-            
-            let status = if item.selected { "选中" } else { "取消选中" };
-            println!("{}: {}", status, item.path);
         }
     }
       /// 运行UI
     pub fn run(&self) -> Result<(), slint::PlatformError> {
         // 确保初始搜索结果为空
-        self.ui.set_search_results(slint::VecModel::from_slice(&[]));
+        self.search_results.clear();
         self.ui.run()
     }
-    
-    /// 获取当前所有目录
-    pub fn get_directories(&self) -> Vec<DirectoryItem> {
-        let mut dirs = Vec::new();
-        for i in 0..self.directories.row_count() {
-            if let Some(item) = self.directories.row_data(i) {
-                dirs.push(item);
-            }
-        }
-        dirs
-    }
-    
-    /// 获取选中的目录
-    pub fn get_selected_directories(&self) -> Vec<DirectoryItem> {
-        self.get_directories()
-            .into_iter()
-            .filter(|dir| dir.selected)
-            .collect()
-    }
-    
-    /// 添加真实目录路径
-    pub fn add_real_directory(&self, path: String) {
-        let new_dir = DirectoryItem {
-            path: path.into(),
-            selected: false,
-        };
-        self.directories.push(new_dir);
-    }
-    
-    /// 清空所有目录
-    pub fn clear_directories(&self) {
-        while self.directories.row_count() > 0 {
-            self.directories.remove(0);
-        }
-    }
       /// 设置过滤器回调
     fn setup_filter_callbacks(&self) {
         let ui_weak = self.ui.as_weak();
@@ -265,39 +514,54 @@ impl UIHandler {    /// 创建新的UI处理器
             }
         });
         
+        // 搜索页面的查询框，查询有效时立即更新过滤设置，无效时只显示错误
+        let ui_weak = self.ui.as_weak();
+        let current_filter = self.current_filter.clone();
+        self.ui.on_handle_query_edited(move |text| {
+            if let Some(ui) = ui_weak.upgrade() {
+                let parsed = parse_query(&text, &current_filter.borrow());
+                match parsed {
+                    Ok(filter) => {
+                        ui.set_saved_filter_data(Self::filter_data_for_ui(&filter));
+                        ui.set_query_error("".into());
+                        *current_filter.borrow_mut() = filter;
+                    }
+                    Err(e) => ui.set_query_error(e.to_string().into()),
+                }
+            }
+        });
+        
+        // 添加/删除正则规则: 规则列表是saved_filter_data中的模型，修改后重新设置给过滤设置页面
+        let ui_weak = self.ui.as_weak();
+        self.ui.on_add_regex_rule(move || {
+            if let Some(ui) = ui_weak.upgrade() {
+                let mut filter_data = ui.get_saved_filter_data();
+                let mut rules: Vec<RegexRuleData> = filter_data.regex_rules.iter().collect();
+                rules.push(RegexRuleData::default());
+                filter_data.regex_rules = Rc::new(VecModel::from(rules)).into();
+                ui.set_saved_filter_data(filter_data);
+            }
+        });
+        
+        let ui_weak = self.ui.as_weak();
+        let current_filter = self.current_filter.clone();
+        self.ui.on_remove_regex_rule(move |index| {
+            if let Some(ui) = ui_weak.upgrade() {
+                let mut filter_data = ui.get_saved_filter_data();
+                let mut rules: Vec<RegexRuleData> = filter_data.regex_rules.iter().collect();
+                if index < 0 || index as usize >= rules.len() {
+                    return;
+                }
+                rules.remove(index as usize);
+                filter_data.regex_rules = Rc::new(VecModel::from(rules)).into();
+                Self::handle_filter_changed(&ui, filter_data, &current_filter);
+            }
+        });
+        
         // 初始化UI的过滤设置为当前保存的值
         self.sync_filter_to_ui();
     }    /// 处理过滤条件变化
     fn handle_filter_changed(ui: &AppWindow, filter_data: FilterData, current_filter: &Rc<std::cell::RefCell<SearchFilter>>) {
-        println!("过滤条件已更改:");
-        println!("  搜索隐藏文件: {}", filter_data.search_hidden_files);
-        println!("  搜索隐藏文件夹: {}", filter_data.search_hidden_folders);
-        println!("  搜索只读文件: {}", filter_data.search_readonly_files);
-        println!("  文件大小范围: {} - {} MB", filter_data.min_file_size, filter_data.max_file_size);
-        println!("  日期限制类型: {:?}", filter_data.date_limit_type);
-        
-        // 打印扩展的日期筛选信息
-        if filter_data.date_limit_type == DateLimitType::Specific {
-            println!("  起始日期: {}/{}/{}", filter_data.specific_year, filter_data.specific_month, filter_data.specific_day);
-            println!("  结束日期: {}/{}/{}", filter_data.end_year, filter_data.end_month, filter_data.end_day);
-        } else if filter_data.date_limit_type != DateLimitType::None {
-            println!("  时间值: {} 单位: {} 内/外: {}", 
-                filter_data.date_limit_value,
-                match filter_data.time_unit {
-                    0 => "天",
-                    1 => "周", 
-                    2 => "月",
-                    3 => "年",
-                    _ => "未知",
-                },
-                if filter_data.time_newer { "内" } else { "外" }
-            );
-        }
-        
-        println!("  正则表达式: '{}'", filter_data.regex_pattern);
-        println!("  正则匹配目标: {:?}", filter_data.regex_target);
-        println!("  记录哈希值: {}", filter_data.record_hash);
-        
         // 转换枚举类型为整数
         let date_limit_type = match filter_data.date_limit_type {
             DateLimitType::None => 0,
@@ -308,63 +572,94 @@ impl UIHandler {    /// 创建新的UI处理器
             DateLimitType::Specific => 4,
         };
         
-        let regex_target = match filter_data.regex_target {
-            RegexTarget::FileName => 0,
-            RegexTarget::FilePath => 1,
-        };        // 直接调用修改后的SearchFilter::from_ui_data函数，传递所有参数
+        let regex_rules = filter_data.regex_rules.iter().map(|rule| RegexRule {
+            pattern: rule.pattern.to_string(),
+            target: match rule.target {
+                1 => RuleTarget::Path,
+                2 => RuleTarget::Parent,
+                3 => RuleTarget::Extension,
+                _ => RuleTarget::Name,
+            },
+            exclude: rule.exclude,
+            case_insensitive: rule.case_insensitive,
+        }).collect();
+        
+        // 直接调用修改后的SearchFilter::from_ui_data函数，传递所有参数
         let filter_result = SearchFilter::from_ui_data(
             filter_data.search_hidden_files,
             filter_data.search_hidden_folders,
             filter_data.search_readonly_files,
-            filter_data.min_file_size,
-            filter_data.max_file_size,
+            filter_data.search_system_files,
+            &filter_data.file_size,
             date_limit_type,
             filter_data.date_limit_value,
             filter_data.specific_year,
             filter_data.specific_month,
             filter_data.specific_day,
-            &filter_data.regex_pattern,
-            regex_target,
+            regex_rules,
+            filter_data.rules_match_all,
+            &filter_data.content_pattern,
             filter_data.record_hash,
             filter_data.time_newer,  // 传入是否"内"/"外"参数
             filter_data.end_year,    // 传入完整日期的结束年
             filter_data.end_month,   // 传入完整日期的结束月
             filter_data.end_day,     // 传入完整日期的结束日
+            filter_data.symlink_mode,
+            &filter_data.include_globs,
+            &filter_data.exclude_globs,
+            &filter_data.excluded_directories,
+            filter_data.respect_ignore_files,
+            &filter_data.include_extensions,
+            &filter_data.exclude_extensions,
+            filter_data.categories.iter().map(|category| FileCategory {
+                name: category.name.to_string(),
+                extensions: parse_extensions(&category.extensions),
+                selected: category.selected,
+            }).collect(),
+            filter_data.time_field,
+            filter_data.content_invert,
+            filter_data.result_kind,
+            filter_data.find_empty,
+            filter_data.junk_as_empty,
+            &filter_data.junk_names,
+            filter_data.hash_algorithm,
         );
         
         match filter_result {
             Ok(filter) => {
-                // 保存过滤器设置到UIHandler的状态中
+                // 保存过滤器设置到UIHandler的状态中，搜索时再据此构建SearchOptions
                 *current_filter.borrow_mut() = filter.clone();
                 
-                // 应用过滤器设置到SearchHelper
-                SearchHelper::apply_filter_settings(&filter);
-                
-                // 更新UI中的保存的过滤器数据
+                // 更新UI中的保存的过滤器数据，并同步搜索页面的查询框
                 ui.set_saved_filter_data(filter_data);
-                
-                println!("✅ 过滤器创建成功并已保存: {:?}", filter);
+                ui.set_query_text(to_query(&filter).into());
+                ui.set_query_error("".into());
             }
             Err(e) => {
-                println!("❌ 创建过滤器失败: {}", e);
+                // 显示在搜索页面上，在改正之前不能开始搜索
+                ui.set_query_error(format!("过滤设置有误: {}", e).into());
             }
         }
-        println!("---");
     }
       /// 将当前保存的过滤器设置同步到UI
     fn sync_filter_to_ui(&self) {
         let filter_data = self.get_filter_data_for_ui();
         self.ui.set_saved_filter_data(filter_data);
-        println!("已将保存的过滤器设置同步到UI");
+        self.ui.set_query_text(to_query(&self.current_filter.borrow()).into());
     }
       /// 获取用于UI初始化的过滤器数据
     pub fn get_filter_data_for_ui(&self) -> FilterData {
-        let filter = self.current_filter.borrow();        FilterData {
+        Self::filter_data_for_ui(&self.current_filter.borrow())
+    }
+    
+    /// 把过滤条件转换为UI中的过滤设置
+    fn filter_data_for_ui(filter: &SearchFilter) -> FilterData {
+        FilterData {
             search_hidden_files: filter.search_hidden_files,
             search_hidden_folders: filter.search_hidden_folders,
             search_readonly_files: filter.search_readonly_files,
-            min_file_size: (filter.min_file_size / (1024 * 1024)) as i32, // 转换为MB
-            max_file_size: (filter.max_file_size / (1024 * 1024)) as i32, // 转换为MB
+            search_system_files: filter.search_system_files,
+            file_size: format_size_range(filter.min_file_size, filter.max_file_size).into(),
             date_limit_type: match filter.date_limit {
                 crate::filter::DateLimitType::None => DateLimitType::None,
                 crate::filter::DateLimitType::Days(..) => DateLimitType::Days,
@@ -394,20 +689,81 @@ impl UIHandler {    /// 创建新的UI处理器
                 _ => filter.start_day.unwrap_or(1),
             },
             // specific_* 已经设置过了，这里只需要设置end_*
-            end_year: 2025,
-            end_month: 6,
-            end_day: 22,
-              // 快速选择设置 - 使用默认值
-            time_unit: 0, // 默认为"天"
-            time_newer: true, // 默认为"内"
+            end_year: match filter.date_limit {
+                crate::filter::DateLimitType::Specific { maximum_year, .. } => maximum_year,
+                _ => filter.end_year.unwrap_or(2025),
+            },
+            end_month: match filter.date_limit {
+                crate::filter::DateLimitType::Specific { maximum_month, .. } => maximum_month as i32,
+                _ => filter.end_month.unwrap_or(6),
+            },
+            end_day: match filter.date_limit {
+                crate::filter::DateLimitType::Specific { maximum_day, .. } => maximum_day as i32,
+                _ => filter.end_day.unwrap_or(22),
+            },
+            // 快速选择设置
+            time_unit: match filter.date_limit {
+                crate::filter::DateLimitType::Weeks(..) => 1,
+                crate::filter::DateLimitType::Months(..) => 2,
+                crate::filter::DateLimitType::Years(..) => 3,
+                _ => 0, // 默认为"天"
+            },
+            time_newer: match filter.date_limit {
+                crate::filter::DateLimitType::Days(_, newer)
+                | crate::filter::DateLimitType::Weeks(_, newer)
+                | crate::filter::DateLimitType::Months(_, newer)
+                | crate::filter::DateLimitType::Years(_, newer) => newer,
+                _ => true, // 默认为"内"
+            },
             
             // 其他设置
-            regex_pattern: filter.regex_pattern.as_ref().map(|r| r.as_str()).unwrap_or("").to_string().into(),
-            regex_target: match filter.regex_target {
-                crate::filter::RegexTarget::FileName => RegexTarget::FileName,
-                crate::filter::RegexTarget::FilePath => RegexTarget::FilePath,
-            },
+            regex_rules: Rc::new(VecModel::from(filter.regex_rules.iter().map(|rule| RegexRuleData {
+                pattern: rule.pattern.clone().into(),
+                target: match rule.target {
+                    RuleTarget::Name => 0,
+                    RuleTarget::Path => 1,
+                    RuleTarget::Parent => 2,
+                    RuleTarget::Extension => 3,
+                },
+                exclude: rule.exclude,
+                case_insensitive: rule.case_insensitive,
+            }).collect::<Vec<_>>())).into(),
+            rules_match_all: filter.rule_mode == RuleMode::All,
+            content_pattern: filter.content_pattern.as_ref().map(|r| r.as_str()).unwrap_or("").to_string().into(),
+            content_invert: filter.content_invert,
             record_hash: filter.record_hash,
+            hash_algorithm: HashAlgorithm::ALL.iter()
+                .position(|algorithm| *algorithm == filter.hash_algorithm)
+                .unwrap_or_default() as i32,
+            result_kind: match filter.result_kind {
+                ResultKind::Files => 0,
+                ResultKind::Directories => 1,
+                ResultKind::Both => 2,
+            },
+            find_empty: filter.find_empty,
+            junk_as_empty: filter.junk_as_empty,
+            junk_names: filter.junk_names.join("; ").into(),
+            symlink_mode: match filter.symlink_mode {
+                SymlinkMode::Skip => 0,
+                SymlinkMode::List => 1,
+                SymlinkMode::Follow => 2,
+            },
+            include_globs: filter.include_globs.join("; ").into(),
+            exclude_globs: filter.exclude_globs.join("; ").into(),
+            excluded_directories: filter.excluded_directories.join("; ").into(),
+            respect_ignore_files: filter.respect_ignore_files,
+            include_extensions: filter.include_extensions.join(" ").into(),
+            exclude_extensions: filter.exclude_extensions.join(" ").into(),
+            time_field: match filter.time_field {
+                TimeField::Modified => 0,
+                TimeField::Created => 1,
+                TimeField::Accessed => 2,
+            },
+            categories: Rc::new(VecModel::from(filter.categories.iter().map(|category| FileCategoryData {
+                name: category.name.clone().into(),
+                extensions: category.extensions.join(" ").into(),
+                selected: category.selected,
+            }).collect::<Vec<_>>())).into(),
         }
     }
       /// 设置搜索相关的回调
@@ -417,20 +773,32 @@ impl UIHandler {    /// 创建新的UI处理器
         let directories = self.directories.clone();
         let current_filter = self.current_filter.clone();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
+        let progress_timer = self.progress_timer.clone();
+        let current_cancel = self.current_cancel.clone();
+        let search_cancel = current_cancel.clone();
+        let duplicates_cancel = current_cancel.clone();
+        let duplicates_timer = progress_timer.clone();
+        let import_checksums_cancel = current_cancel.clone();
+        let export_checksums_cancel = current_cancel.clone();
           // 1. 搜索按钮回调
         let search_callback = move || {
             if let Some(_ui) = ui_weak.upgrade() {
-                // 获取要搜索的目录
-                let mut paths: Vec<PathBuf> = Vec::new();
+                // 上一次搜索尚未结束
+                if _ui.get_search_running() {
+                    return;
+                }
+                
+                // 获取要搜索的目录及各自的深度范围
+                let mut roots: Vec<SearchRoot> = Vec::new();
                 for i in 0..directories.row_count() {
-                    if let Some(dir) = directories.row_data(i) {
-                        if dir.selected {
-                            paths.push(PathBuf::from(dir.path.as_str()));
-                        }
+                    if let Some(dir) = directories.row_data(i)
+                        && dir.selected {
+                        roots.push(search_root(&dir));
                     }
                 }
                 
-                if paths.is_empty() {
+                if roots.is_empty() {
                     MessageDialog::new()
                         .set_type(MessageType::Info)
                         .set_title("提示")
@@ -440,61 +808,144 @@ impl UIHandler {    /// 创建新的UI处理器
                     return;
                 }
                 
+                // 查询有误时不使用上一次有效的过滤条件搜索，以免结果与查询框不符
+                let query_error = _ui.get_query_error();
+                if !query_error.is_empty() {
+                    MessageDialog::new()
+                        .set_type(MessageType::Warning)
+                        .set_title("查询有误")
+                        .set_text(&query_error)
+                        .show_alert()
+                        .unwrap();
+                    return;
+                }
+                
+                // 最小深度大于最大深度的目录不会有任何结果
+                if let Some(root) = roots.iter().find(|root| root.min_depth > root.max_depth) {
+                    MessageDialog::new()
+                        .set_type(MessageType::Warning)
+                        .set_title("深度有误")
+                        .set_text(&format!("目录 {} 的最小深度({})大于最大深度({})", root.path.display(), root.min_depth, root.max_depth))
+                        .show_alert()
+                        .unwrap();
+                    return;
+                }
+                
                 // 获取过滤条件
                 let filter = current_filter.borrow().clone();
                 
-                // 检索文件
-                let search_depth = 100; // 最大搜索深度
-                let regex_str = match &filter.regex_pattern {
-                    Some(pattern) => pattern.as_str().to_string(),
-                    None => String::new(),
-                };
-                
-                // 使用SearchHelper执行搜索
-                let found_files = crate::helper::SearchHelper::perform_search(
-                    &paths, 
-                    search_depth, 
-                    &regex_str,
-                    &filter
-                );
-                println!("找到 {} 个文件", found_files.len());
-                  // 清空之前的搜索结果
-                while search_results.row_count() > 0 {
-                    search_results.remove(0);
-                }
+                // 清空之前的搜索结果，新的结果会在搜索过程中逐批加入
+                replace_results(&search_results, &result_files, Vec::new());
+                _ui.set_selected_count(0);
+                _ui.set_search_errors(slint::VecModel::from_slice(&[]));
+                _ui.set_search_incomplete(false);
+                _ui.set_search_running(true);
+                _ui.set_search_status("正在搜索...".into());
                 
-                // 添加搜索结果到UI
-                for file in &found_files {
-                    let file_info = FileInfo {
-                        path: file.path.to_string_lossy().to_string().into(),
-                        name: file.name.clone().into(),
-                        size: file.size as i32,
-                        time: file.time as i32,
-                        hash: file.hash.clone().into(),
-                        selected: false, // 默认不选中
-                    };
-                    search_results.push(file_info);
-                }
-                  println!("搜索完成，找到 {} 个文件", search_results.row_count());
+                let cancel = CancelToken::new();
+                *search_cancel.borrow_mut() = Some(cancel.clone());
                 
-                // 先把结果转换为Vec后再传递给UI
-                let mut file_infos = Vec::new();
-                for i in 0..search_results.row_count() {
-                    if let Some(info) = search_results.row_data(i) {
-                        file_infos.push(info);
+                // 定时刷新搜索进度
+                let progress = Arc::new(SearchProgress::default());
+                let started_at = Instant::now();
+                let timer_weak = Rc::downgrade(&progress_timer);
+                let timer_ui = _ui.as_weak();
+                let timer_progress = progress.clone();
+                progress_timer.start(slint::TimerMode::Repeated, PROGRESS_INTERVAL, move || {
+                    let Some(ui) = timer_ui.upgrade() else { return };
+                    if !ui.get_search_running() {
+                        if let Some(timer) = timer_weak.upgrade() {
+                            timer.stop();
+                        }
+                        return;
                     }
-                }
+                    ui.set_search_status(format_search_progress(&timer_progress, started_at.elapsed()).into());
+                });
                 
-                // 使用from_slice方法创建一个新的VecModel直接传递给UI
-                _ui.set_search_results(slint::VecModel::from_slice(&file_infos));
+                // 在后台线程中执行搜索，避免阻塞UI
+                let ui_weak = _ui.as_weak();
+                let result_files = result_files.clone();
+                thread::spawn(move || {
+                    let pending = Mutex::new((Vec::new(), Instant::now()));
+                    
+                    // 使用SearchHelper执行搜索，每批结果攒够一定数量或时间后再交给UI线程
+                    let report = SearchHelper::perform_search_streaming(
+                        &roots, 
+                        &filter,
+                        &progress,
+                        &cancel,
+                        &|files| {
+                            let mut pending = pending.lock().unwrap();
+                            pending.0.extend(store_files(&result_files, files));
+                            if pending.0.len() >= RESULT_BATCH_SIZE || pending.1.elapsed() >= PROGRESS_INTERVAL {
+                                let rows = std::mem::take(&mut pending.0);
+                                pending.1 = Instant::now();
+                                append_results_in_event_loop(&ui_weak, rows);
+                            }
+                        }
+                    );
+                    let report = match report {
+                        Ok(report) => report,
+                        Err(e) => {
+                            // 正则表达式无效时不会开始搜索，错误与无法读取的路径一样显示在状态区域
+                            let search_errors = [SharedString::from(format!("无效的正则表达式: {}", e))];
+                            let _ = slint::invoke_from_event_loop(move || {
+                                if let Some(ui) = ui_weak.upgrade() {
+                                    ui.set_search_errors(slint::VecModel::from_slice(&search_errors));
+                                    ui.set_search_status("搜索失败".into());
+                                    ui.set_search_running(false);
+                                }
+                            });
+                            return;
+                        }
+                    };
+                    let (rows, _) = pending.into_inner().unwrap();
+                    append_results_in_event_loop(&ui_weak, rows);
+                    
+                    let elapsed = started_at.elapsed();
+                    
+                    // 显示无法读取的路径
+                    let search_errors: Vec<SharedString> = report.errors
+                        .iter()
+                        .map(|error| error.to_string().into())
+                        .collect();
+                    // 被停止的搜索保留已找到的结果，但标记为不完整
+                    let incomplete = report.cancelled;
+                    let status = if incomplete {
+                        format!("搜索已停止: {}", format_search_progress(&progress, elapsed))
+                    } else {
+                        format!("搜索完成: {}", format_search_progress(&progress, elapsed))
+                    };
+                    let _ = slint::invoke_from_event_loop(move || {
+                        if let Some(ui) = ui_weak.upgrade() {
+                            ui.set_search_errors(slint::VecModel::from_slice(&search_errors));
+                            ui.set_search_incomplete(incomplete);
+                            ui.set_search_status(status.into());
+                            ui.set_search_running(false);
+                        }
+                    });
+                });
+            }
+        };
+        
+        // 停止搜索按钮回调
+        let stop_search_callback = move || {
+            if let Some(cancel) = current_cancel.borrow_mut().take() {
+                cancel.cancel();
             }
         };
         
         // 2. 导入结果按钮回调
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
         let import_callback = move || {
-            if let Some(_ui) = ui_weak.upgrade() {                // 弹出文件选择对话框
+            if let Some(_ui) = ui_weak.upgrade() {
+                // 搜索过程中结果还在不断加入，不能替换
+                if _ui.get_search_running() {
+                    return;
+                }
+                // 弹出文件选择对话框
                 let dialog = FileDialog::new();
                 let result = dialog.show_open_single_file();
                 match result {
@@ -504,34 +955,13 @@ impl UIHandler {    /// 创建新的UI处理器
                             Ok(json_str) => {
                                 // 解析JSON为Vec<SingleFileInformations>
                                 match serde_json::from_str::<Vec<SingleFileInformations>>(&json_str) {                                    Ok(files) => {
-                                        // 清空之前的搜索结果
-                                        while search_results.row_count() > 0 {
-                                            search_results.remove(0);
-                                        }
-                                          // 添加导入的结果
-                                        for file in &files {
-                                            let file_info = FileInfo {
-                                                path: file.path.to_string_lossy().to_string().into(),
-                                                name: file.name.clone().into(),
-                                                size: file.size as i32,
-                                                time: file.time as i32,
-                                                hash: file.hash.clone().into(),
-                                                selected: false, // 默认不选中
-                                            };
-                                            search_results.push(file_info);
-                                        }
-                                          // 先把结果转换为Vec后再传递给UI
-                                        let mut file_infos = Vec::new();
-                                        for i in 0..search_results.row_count() {
-                                            if let Some(info) = search_results.row_data(i) {
-                                                file_infos.push(info);
-                                            }
-                                        }
+                                        // 用导入的结果替换之前的搜索结果，UI绑定的模型会自动刷新
+                                        replace_results(&search_results, &result_files, files);
+                                        _ui.set_selected_count(0);
                                         
-                                        // 使用from_slice方法创建一个新的VecModel直接传递给UI
-                                        _ui.set_search_results(slint::VecModel::from_slice(&file_infos));
-                                        
-                                        println!("成功导入 {} 个搜索结果", files.len());
+                                        // 导入的结果没有对应的搜索报告
+                                        _ui.set_search_errors(slint::VecModel::from_slice(&[]));
+                                        _ui.set_search_incomplete(false);
                                     },
                                     Err(e) => {
                                         MessageDialog::new()
@@ -571,7 +1001,6 @@ impl UIHandler {    /// 创建新的UI处理器
         // 3. 设置目标文件夹按钮回调
         let ui_weak = self.ui.as_weak();
         let selected_paths = self.selected_paths.clone();
-        let search_results = self.search_results.inner.clone();
         let select_folder_callback = move || {
             if let Some(_ui) = ui_weak.upgrade() {
                 match FileDialog::new()
@@ -585,21 +1014,7 @@ impl UIHandler {    /// 创建新的UI处理器
                         }
                         
                         // 添加新选择的路径
-                        selected_paths.push(path_str.clone().into());
-                        
-                        // 打印当前选择的目标文件夹路径
-                        println!("已设置目标文件夹: {}", path_str);
-                        
-                        // 打印当前选中的搜索结果文件列表
-                        let mut selected_files = Vec::new();
-                        for i in 0..search_results.row_count() {
-                            if let Some(file_info) = search_results.row_data(i) {
-                                if file_info.selected {
-                                    selected_files.push(file_info.path.as_str().to_string());
-                                }
-                            }
-                        }
-                        println!("当前选中的文件: {:?}", selected_files);
+                        selected_paths.push(path_str.into());
                     },
                     Ok(None) => {
                         // 用户取消了选择
@@ -619,23 +1034,17 @@ impl UIHandler {    /// 创建新的UI处理器
         // 3.5 复制选中文件按钮回调
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
         let selected_paths = self.selected_paths.clone();
         let copy_files_callback = move || {
-            if let Some(ui) = ui_weak.upgrade() {
+            if let Some(_ui) = ui_weak.upgrade() {
                 // 获取选中的文件
                 let mut files = Vec::new();
                 
                 for i in 0..search_results.row_count() {
-                    if let Some(file_info) = search_results.row_data(i) {
-                        if file_info.selected {  // 只处理已选中的文件
-                            files.push(SingleFileInformations {
-                                path: PathBuf::from(file_info.path.as_str()),
-                                name: file_info.name.as_str().to_string(),
-                                size: file_info.size as u64,
-                                time: file_info.time as u64,
-                                hash: file_info.hash.as_str().to_string(),
-                            });
-                        }
+                    if let Some(file_info) = search_results.row_data(i)
+                        && file_info.selected {  // 只处理已选中的文件
+                        files.push(stored_file(&result_files, &file_info));
                     }
                 }
                 
@@ -701,6 +1110,7 @@ impl UIHandler {    /// 创建新的UI处理器
         // 3.6 移动选中文件按钮回调
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
         let selected_paths = self.selected_paths.clone();
         let move_files_callback = move || {
             if let Some(ui) = ui_weak.upgrade() {
@@ -709,17 +1119,10 @@ impl UIHandler {    /// 创建新的UI处理器
                 let mut indices_to_remove = Vec::new();
                 
                 for i in 0..search_results.row_count() {
-                    if let Some(file_info) = search_results.row_data(i) {
-                        if file_info.selected {  // 只处理已选中的文件
-                            files.push(SingleFileInformations {
-                                path: PathBuf::from(file_info.path.as_str()),
-                                name: file_info.name.as_str().to_string(),
-                                size: file_info.size as u64,
-                                time: file_info.time as u64,
-                                hash: file_info.hash.as_str().to_string(),
-                            });
-                            indices_to_remove.push(i);
-                        }
+                    if let Some(file_info) = search_results.row_data(i)
+                        && file_info.selected {  // 只处理已选中的文件
+                        files.push(stored_file(&result_files, &file_info));
+                        indices_to_remove.push(i);
                     }
                 }
                 
@@ -774,15 +1177,7 @@ impl UIHandler {    /// 创建新的UI处理器
                                 for index in indices_to_remove {
                                     search_results.remove(index);
                                 }
-                                
-                                // 更新UI显示
-                                let mut file_infos = Vec::new();
-                                for i in 0..search_results.row_count() {
-                                    if let Some(info) = search_results.row_data(i) {
-                                        file_infos.push(info);
-                                    }
-                                }
-                                ui.set_search_results(slint::VecModel::from_slice(&file_infos));
+                                ui.set_selected_count(0);
                             },
                             Err(e) => {
                                 MessageDialog::new()
@@ -801,6 +1196,7 @@ impl UIHandler {    /// 创建新的UI处理器
         // 4. 删除选中文件按钮回调
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
         let delete_files_callback = move || {
             if let Some(ui) = ui_weak.upgrade() {
                 // 获取选中的文件
@@ -808,17 +1204,10 @@ impl UIHandler {    /// 创建新的UI处理器
                 let mut indices_to_remove = Vec::new();
                 
                 for i in 0..search_results.row_count() {
-                    if let Some(file_info) = search_results.row_data(i) {
-                        if file_info.selected {  // 只处理已选中的文件
-                            files.push(SingleFileInformations {
-                                path: PathBuf::from(file_info.path.as_str()),
-                                name: file_info.name.as_str().to_string(),
-                                size: file_info.size as u64,
-                                time: file_info.time as u64,
-                                hash: file_info.hash.as_str().to_string(),
-                            });
-                            indices_to_remove.push(i);
-                        }
+                    if let Some(file_info) = search_results.row_data(i)
+                        && file_info.selected {  // 只处理已选中的文件
+                        files.push(stored_file(&result_files, &file_info));
+                        indices_to_remove.push(i);
                     }
                 }
                 
@@ -842,40 +1231,36 @@ impl UIHandler {    /// 创建新的UI处理器
                     .unwrap_or(false);
                 
                 if confirm {
-                    // 使用SearchHelper删除文件
-                    match SearchHelper::delete_files(&files) {
-                        Ok(_) => {
-                            MessageDialog::new()
-                                .set_type(MessageType::Info)
-                                .set_title("删除成功")
-                                .set_text(&format!("成功删除 {} 个文件", files.len()))
-                                .show_alert()
-                                .unwrap();
-                                
-                            // 从搜索结果中移除已删除的文件
-                            // 从后向前移除，避免索引问题
-                            indices_to_remove.sort_by(|a, b| b.cmp(a));
-                            for index in indices_to_remove {
-                                search_results.remove(index);
-                            }
-                            
-                            // 更新UI显示
-                            let mut file_infos = Vec::new();
-                            for i in 0..search_results.row_count() {
-                                if let Some(info) = search_results.row_data(i) {
-                                    file_infos.push(info);
-                                }
-                            }
-                            ui.set_search_results(slint::VecModel::from_slice(&file_infos));
-                        },
-                        Err(e) => {
-                            MessageDialog::new()
-                                .set_type(MessageType::Error)
-                                .set_title("删除失败")
-                                .set_text(&format!("删除文件时出错: {}", e))
-                                .show_alert()
-                                .unwrap();
-                        }
+                    // 使用SearchHelper删除文件，失败的条目不影响其余条目
+                    let report = SearchHelper::delete_files(&files);
+                    
+                    // 从搜索结果中移除已删除的文件(包括随所在目录一起删除的)
+                    // 从后向前移除，避免索引问题
+                    let mut indices_to_remove: Vec<usize> = indices_to_remove.into_iter()
+                        .zip(&files)
+                        .filter(|(_, file)| report.processed.iter().any(|path| file.path.starts_with(path)))
+                        .map(|(index, _)| index)
+                        .collect();
+                    indices_to_remove.sort_by(|a, b| b.cmp(a));
+                    for index in indices_to_remove {
+                        search_results.remove(index);
+                    }
+                    ui.set_selected_count(search_results.iter().filter(|file_info| file_info.selected).count() as i32);
+                    
+                    if report.errors.is_empty() {
+                        MessageDialog::new()
+                            .set_type(MessageType::Info)
+                            .set_title("删除成功")
+                            .set_text(&format!("成功删除 {} 个文件", files.len()))
+                            .show_alert()
+                            .unwrap();
+                    } else {
+                        MessageDialog::new()
+                            .set_type(MessageType::Warning)
+                            .set_title("删除失败")
+                            .set_text(&format!("已删除 {} 个文件{}", report.processed.len(), format_errors(&report.errors)))
+                            .show_alert()
+                            .unwrap();
                     }
                 }
             }
@@ -884,7 +1269,7 @@ impl UIHandler {    /// 创建新的UI处理器
         // 5. 映射文件按钮回调
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
-        let selected_paths = self.selected_paths.clone();
+        let result_files = self.search_results.files.clone();
         let map_files_callback = move || {
             if let Some(_ui) = ui_weak.upgrade() {
                 if search_results.row_count() == 0 {
@@ -936,29 +1321,37 @@ impl UIHandler {    /// 创建新的UI处理器
                                 // 获取搜索结果
                                 let mut files = Vec::new();
                                 for i in 0..search_results.row_count() {
-                                    if let Some(file_info) = search_results.row_data(i) {
-                                        if file_info.selected {
-                                            // 只处理已选中的文件
-                                            files.push(SingleFileInformations {
-                                                path: PathBuf::from(file_info.path.as_str()),
-                                                name: file_info.name.as_str().to_string(),
-                                                size: file_info.size as u64,
-                                                time: file_info.time as u64,
-                                                hash: file_info.hash.as_str().to_string(),
-                                            });
-                                        }
+                                    if let Some(file_info) = search_results.row_data(i)
+                                        && file_info.selected {
+                                        // 只处理已选中的文件
+                                        files.push(stored_file(&result_files, &file_info));
                                     }
                                 }
 
-                                // 使用SearchHelper执行映射
-                                SearchHelper::map_files(&files, &source_path, &dest_path);
-
-                                MessageDialog::new()
-                                    .set_type(MessageType::Info)
-                                    .set_title("映射完成")
-                                    .set_text(&format!("成功映射 {} 个文件到 {}", files.len(), dest_path.to_string_lossy()))
-                                    .show_alert()
-                                    .unwrap();
+                                // 使用SearchHelper执行映射，失败的条目不影响其余条目
+                                match SearchHelper::map_files(&files, &source_path, &dest_path) {
+                                    Ok(report) => {
+                                        let mut text = format!("成功映射 {} 个文件到 {}", report.processed.len(), dest_path.to_string_lossy());
+                                        if !report.skipped.is_empty() {
+                                            text.push_str(&format!("\n{} 个文件不在起始文件夹中，未映射", report.skipped.len()));
+                                        }
+                                        text.push_str(&format_errors(&report.errors));
+                                        MessageDialog::new()
+                                            .set_type(if report.errors.is_empty() { MessageType::Info } else { MessageType::Warning })
+                                            .set_title("映射完成")
+                                            .set_text(&text)
+                                            .show_alert()
+                                            .unwrap();
+                                    },
+                                    Err(e) => {
+                                        MessageDialog::new()
+                                            .set_type(MessageType::Error)
+                                            .set_title("映射失败")
+                                            .set_text(&format!("无法创建目标文件夹: {}", e))
+                                            .show_alert()
+                                            .unwrap();
+                                    }
+                                }
                             },
                             Ok(None) => {
                                 // 用户取消了选择
@@ -989,110 +1382,100 @@ impl UIHandler {    /// 创建新的UI处理器
         };
         
         // 6. 去重展示按钮回调
+        // 重复文件查找需要读取文件内容，在后台线程中进行，可以用停止按钮中途取消
+        // 找到的重复文件按组显示在重复文件视图中，不会从结果列表中移除
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
+        let duplicate_groups = self.duplicate_groups.clone();
+        let current_filter_for_duplicates = self.current_filter.clone();
         let remove_duplicates_callback = move || {
             if let Some(updated_ui) = ui_weak.upgrade() {
+                if updated_ui.get_search_running() {
+                    return;
+                }
                 // 获取搜索结果
-            let mut files = Vec::new();
-            for i in 0..search_results.row_count() {
-                if let Some(file_info) = search_results.row_data(i) {
-                    files.push(SingleFileInformations {
-                        path: PathBuf::from(file_info.path.as_str()),
-                        name: file_info.name.as_str().to_string(),
-                        size: file_info.size as u64,
-                        time: file_info.time as u64,
-                        hash: file_info.hash.as_str().to_string(),
-                    });
+                let mut files = Vec::new();
+                for i in 0..search_results.row_count() {
+                    if let Some(file_info) = search_results.row_data(i) {
+                        files.push(stored_file(&result_files, &file_info));
+                    }
                 }
-            }
-            
-            if files.is_empty() {
-                MessageDialog::new()
-                    .set_type(MessageType::Info)
-                    .set_title("提示")
-                    .set_text("没有搜索结果可以进行去重！")
-                    .show_alert()
-                    .unwrap();
-                return;
-            }
-            
-            if files[0].hash.is_empty() {
-                MessageDialog::new()
-                    .set_type(MessageType::Info)
-                    .set_title("提示")
-                    .set_text("搜索结果中没有哈希值，请在过滤设置中启用'记录哈希值'选项！")
-                    .show_alert()
-                    .unwrap();
-                return;
-            }
-            
-            // MessageDialog::new()
-            //     .set_type(MessageType::Info)
-            //     .set_title("提示")
-            //     .set_text("正在进行去重处理，请稍候...")
-            //     .show_alert()
-            //     .unwrap();
-
-            // 去重处理 - 使用SearchHelper
-            let original_count = files.len();
-            crate::search_file::unique_files(&mut files);
-            let unique_count = files.len();
-
-            // 打印去重结果
-            println!("去重前文件数量: {}", original_count);
-            println!("去重后文件数量: {}", unique_count);
-            for file in &files {
-                println!("保留文件: {} - SHA256:{}", file.name, file.hash);
-            }
-            
-            if original_count == unique_count {
-                MessageDialog::new()
-                    .set_type(MessageType::Info)
-                    .set_title("去重结果")
-                    .set_text("没有重复文件，去重操作未做任何改变。")
-                    .show_alert()
-                    .unwrap();
-                return;
-            }
-
-            // // 更新搜索结果
-            
-            while search_results.row_count() > 0 {
-                search_results.remove(search_results.row_count() as usize - 1);
-            }
-
-            println!("清空之前的搜索结果");
-
-            // while search_results.row_count() > 0 {
-            //     search_results.remove(0);
-            // }
-            let mut file_infos = Vec::new();
-            for file in &files {
-                let file_info = FileInfo {
-                    path: file.path.to_string_lossy().to_string().into(),
-                    name: file.name.clone().into(),
-                    size: file.size as i32,
-                    time: file.time as i32,
-                    hash: file.hash.clone().into(),
-                    selected: false, // 默认不选中
-                };
-                search_results.push(file_info.clone());
-                file_infos.push(file_info);
-            }
-
-            // 显示转化为Vec<FileInfo>后的结果
-            println!("转换为Vec后，搜索结果数量: {}", file_infos.len());
-            
-            // 使用已有的UI引用设置搜索结果
-            updated_ui.set_search_results(slint::VecModel::from_slice(&file_infos));
-            
-            MessageDialog::new()
-                .set_type(MessageType::Info)
-                .set_title("去重完成")
-                .set_text(&format!("原有 {} 个文件，去重后剩余 {} 个文件", original_count, unique_count))
-                .show_alert()
-                .unwrap();
+                
+                if files.is_empty() {
+                    MessageDialog::new()
+                        .set_type(MessageType::Info)
+                        .set_title("提示")
+                        .set_text("没有搜索结果可以进行去重！")
+                        .show_alert()
+                        .unwrap();
+                    return;
+                }
+                
+                updated_ui.set_search_running(true);
+                updated_ui.set_search_status("正在查找重复文件...".into());
+                let cancel = CancelToken::new();
+                *duplicates_cancel.borrow_mut() = Some(cancel.clone());
+                
+                // 定时刷新比较进度
+                let progress = Arc::new(DuplicateProgress::default());
+                let algorithm = current_filter_for_duplicates.borrow().hash_algorithm;
+                let timer_weak = Rc::downgrade(&duplicates_timer);
+                let timer_ui = updated_ui.as_weak();
+                let timer_progress = progress.clone();
+                duplicates_timer.start(slint::TimerMode::Repeated, PROGRESS_INTERVAL, move || {
+                    let Some(ui) = timer_ui.upgrade() else { return };
+                    if !ui.get_search_running() {
+                        if let Some(timer) = timer_weak.upgrade() {
+                            timer.stop();
+                        }
+                        return;
+                    }
+                    ui.set_search_status(format_duplicate_progress(&timer_progress).into());
+                });
+                
+                let ui_weak = updated_ui.as_weak();
+                let duplicate_groups = duplicate_groups.clone();
+                thread::spawn(move || {
+                    let (groups, report) = SearchHelper::find_duplicates(&mut files, algorithm, &progress, &cancel);
+                    let group_count = groups.len();
+                    *duplicate_groups.lock().unwrap() = crate::search_file::duplicates::into_groups(&files, &groups);
+                    let search_errors: Vec<SharedString> = report.errors
+                        .iter()
+                        .map(|error| error.to_string().into())
+                        .collect();
+                    let cancelled = report.cancelled;
+                    
+                    let _ = slint::invoke_from_event_loop(move || {
+                        let Some(ui) = ui_weak.upgrade() else { return };
+                        ui.set_search_running(false);
+                        ui.set_search_errors(slint::VecModel::from_slice(&search_errors));
+                        if cancelled {
+                            ui.set_search_status("查找重复文件已停止".into());
+                            return;
+                        }
+                        ui.set_search_status(format!("查找重复文件完成: 找到 {} 组重复文件", group_count).into());
+                        
+                        if group_count == 0 {
+                            MessageDialog::new()
+                                .set_type(MessageType::Info)
+                                .set_title("去重结果")
+                                .set_text("没有重复文件。")
+                                .show_alert()
+                                .unwrap();
+                            return;
+                        }
+                        
+                        // 按当前选择的规则标记每组保留的文件，然后显示重复文件视图
+                        let mut groups = duplicate_groups.lock().unwrap();
+                        let policy = keeper_policy(ui.get_keeper_policy(), ui.get_preferred_folder().as_str());
+                        for group in groups.iter_mut() {
+                            group.apply_policy(&policy);
+                        }
+                        refresh_duplicate_view(&ui, &groups);
+                        ui.set_show_duplicates(true);
+                    });
+                });
             }
         };
         
@@ -1100,6 +1483,8 @@ impl UIHandler {    /// 创建新的UI处理器
 
         
         // 8. 打开文件夹回调
+        // 文件管理器独立运行，不需要等待它退出
+        #[allow(clippy::zombie_processes)]
         let open_folder_callback = move |path: SharedString| {
             let path_str = path.as_str();
             let path_obj = PathBuf::from(path_str);
@@ -1133,6 +1518,7 @@ impl UIHandler {    /// 创建新的UI处理器
         // 新增导出结果按钮回调
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
         let export_callback = move || {
             if let Some(_ui) = ui_weak.upgrade() {
                 // 检查是否有搜索结果
@@ -1155,13 +1541,7 @@ impl UIHandler {    /// 创建新的UI处理器
                     let mut files = Vec::new();
                     for i in 0..search_results.row_count() {
                         if let Some(file_info) = search_results.row_data(i) {
-                            files.push(SingleFileInformations {
-                                path: PathBuf::from(file_info.path.as_str()),
-                                name: file_info.name.as_str().to_string(),
-                                size: file_info.size as u64,
-                                time: file_info.time as u64,
-                                hash: file_info.hash.as_str().to_string(),
-                            });
+                            files.push(stored_file(&result_files, &file_info));
                         }
                     }
                     
@@ -1188,9 +1568,147 @@ impl UIHandler {    /// 创建新的UI处理器
             }
         };
         
+        // 导入校验文件按钮回调
+        // 逐个读取校验文件中列出的文件并与记录的哈希值比较，存在的文件替换当前的结果列表
+        // 内容不一致和已经不存在的文件列在错误列表中；校验在后台线程中进行，可以用停止按钮中途取消
+        let ui_weak = self.ui.as_weak();
+        let result_files = self.search_results.files.clone();
+        let import_checksums_callback = move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            if ui.get_search_running() {
+                return;
+            }
+            let manifest = match FileDialog::new().show_open_single_file() {
+                Ok(Some(manifest)) => manifest,
+                Ok(None) => return,
+                Err(e) => {
+                    MessageDialog::new()
+                        .set_type(MessageType::Error)
+                        .set_title("错误")
+                        .set_text(&format!("文件对话框错误: {}", e))
+                        .show_alert()
+                        .unwrap();
+                    return;
+                }
+            };
+            
+            ui.set_search_running(true);
+            ui.set_search_status(format!("正在校验 {} 中的文件...", manifest.display()).into());
+            let cancel = CancelToken::new();
+            *import_checksums_cancel.borrow_mut() = Some(cancel.clone());
+            
+            let ui_weak = ui.as_weak();
+            let result_files = result_files.clone();
+            thread::spawn(move || {
+                let outcome = SearchHelper::import_checksums(&manifest, &cancel);
+                let outcome = outcome.map(|import| {
+                    let mut errors: Vec<SharedString> = import.mismatched.iter()
+                        .map(|path| format!("内容不一致: {}", path.display()).into())
+                        .collect();
+                    errors.extend(import.missing.iter().map(|path| format!("文件不存在: {}", path.display()).into()));
+                    errors.extend(import.report.errors.iter().map(|error| error.to_string().into()));
+                    let status = format_checksum_import(&import);
+                    (import.files, errors, status, import.report.cancelled)
+                });
+                let _ = slint::invoke_from_event_loop(move || {
+                    let Some(ui) = ui_weak.upgrade() else { return };
+                    ui.set_search_running(false);
+                    match outcome {
+                        Ok((files, errors, status, cancelled)) => {
+                            // 用校验的文件替换之前的结果，UI绑定的模型会自动刷新
+                            let results = ui.get_search_results();
+                            if let Some(model) = results.as_any().downcast_ref::<VecModel<FileInfo>>() {
+                                replace_results(model, &result_files, files);
+                            }
+                            ui.set_selected_count(0);
+                            ui.set_search_errors(slint::VecModel::from_slice(&errors));
+                            ui.set_search_incomplete(cancelled);
+                            ui.set_search_status(status.into());
+                        },
+                        Err(e) => {
+                            ui.set_search_status("".into());
+                            MessageDialog::new()
+                                .set_type(MessageType::Error)
+                                .set_title("导入失败")
+                                .set_text(&format!("读取校验文件失败: {}", e))
+                                .show_alert()
+                                .unwrap();
+                        }
+                    }
+                });
+            });
+        };
+        
+        // 导出校验文件按钮回调
+        // 使用过滤设置中选择的哈希算法，保存的文件名以.md5/.sha1等结尾时使用对应的算法
+        // 已经用同一算法记录了哈希值的文件不再读取，其他文件在后台线程中计算
+        let ui_weak = self.ui.as_weak();
+        let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
+        let current_filter_for_checksums = self.current_filter.clone();
+        let export_checksums_callback = move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            if ui.get_search_running() {
+                return;
+            }
+            if search_results.row_count() == 0 {
+                MessageDialog::new()
+                    .set_type(MessageType::Info)
+                    .set_title("提示")
+                    .set_text("没有搜索结果可以导出！")
+                    .show_alert()
+                    .unwrap();
+                return;
+            }
+            let algorithm = current_filter_for_checksums.borrow().hash_algorithm;
+            let filename = format!("checksums.{}", algorithm.as_str());
+            let dialog = FileDialog::new().set_filename(&filename);
+            let Ok(Some(manifest)) = dialog.show_save_single_file() else { return };
+            let files: Vec<SingleFileInformations> = search_results.iter().map(|file_info| stored_file(&result_files, &file_info)).collect();
+            
+            ui.set_search_running(true);
+            ui.set_search_status("正在计算哈希值...".into());
+            let cancel = CancelToken::new();
+            *export_checksums_cancel.borrow_mut() = Some(cancel.clone());
+            
+            let ui_weak = ui.as_weak();
+            thread::spawn(move || {
+                let outcome = SearchHelper::export_checksums(&files, &manifest, algorithm, &cancel);
+                let outcome = outcome.map(|export| {
+                    let errors: Vec<SharedString> = export.report.errors.iter().map(|error| error.to_string().into()).collect();
+                    (export.written, errors, export.report.cancelled)
+                });
+                let _ = slint::invoke_from_event_loop(move || {
+                    let Some(ui) = ui_weak.upgrade() else { return };
+                    ui.set_search_running(false);
+                    match outcome {
+                        Ok((_, _, true)) => ui.set_search_status("导出校验文件已停止，没有写入文件".into()),
+                        Ok((written, errors, false)) => {
+                            ui.set_search_errors(slint::VecModel::from_slice(&errors));
+                            let mut status = format!("已将 {} 个文件的哈希值导出到 {}", written, manifest.display());
+                            if !errors.is_empty() {
+                                status.push_str(&format!("，{} 个文件无法读取", errors.len()));
+                            }
+                            ui.set_search_status(status.into());
+                        },
+                        Err(e) => {
+                            ui.set_search_status("".into());
+                            MessageDialog::new()
+                                .set_type(MessageType::Error)
+                                .set_title("导出失败")
+                                .set_text(&format!("写入校验文件失败: {}", e))
+                                .show_alert()
+                                .unwrap();
+                        }
+                    }
+                });
+            });
+        };
+        
         // 新增树状显示按钮回调
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
         let show_tree_view_callback = move || {
             if let Some(_ui) = ui_weak.upgrade() {
                 // 检查是否有搜索结果
@@ -1208,13 +1726,7 @@ impl UIHandler {    /// 创建新的UI处理器
                 let mut files = Vec::new();
                 for i in 0..search_results.row_count() {
                     if let Some(file_info) = search_results.row_data(i) {
-                        files.push(SingleFileInformations {
-                            path: PathBuf::from(file_info.path.as_str()),
-                            name: file_info.name.as_str().to_string(),
-                            size: file_info.size as u64,
-                            time: file_info.time as u64,
-                            hash: file_info.hash.as_str().to_string(),
-                        });
+                        files.push(stored_file(&result_files, &file_info));
                     }
                 }
                 
@@ -1230,7 +1742,7 @@ impl UIHandler {    /// 创建新的UI处理器
                             MessageDialog::new()
                                 .set_type(MessageType::Info)
                                 .set_title("树状结构生成成功")
-                                .set_text(&format!("成功生成树状结构!"))
+                                .set_text("成功生成树状结构!")
                                 .show_alert()
                                 .unwrap();
                         },
@@ -1257,39 +1769,17 @@ impl UIHandler {    /// 创建新的UI处理器
                     file_info.selected = selected;
                     search_results.set_row_data(index as usize, file_info);
                     
-                    // 计算已选择的数量并打印
+                    // 计算已选择的数量
                     let mut selected_count = 0;
                     for i in 0..search_results.row_count() {
-                        if let Some(info) = search_results.row_data(i) {
-                            if info.selected {
-                                selected_count += 1;
-                            }
-                        }
-                    }
-                    println!("已选择 {}/{} 个文件", selected_count, search_results.row_count());
-                    
-                    // 打印当前选中的文件列表
-                    let mut selected_files = Vec::new();
-                    for i in 0..search_results.row_count() {
-                        if let Some(file_info) = search_results.row_data(i) {
-                            if file_info.selected {
-                                selected_files.push(file_info.path.as_str().to_string());
-                            }
+                        if let Some(info) = search_results.row_data(i)
+                            && info.selected {
+                            selected_count += 1;
                         }
                     }
-                    println!("当前选中的文件: {:?}", selected_files);
                     
                     // 更新UI中的选中计数
                     ui.set_selected_count(selected_count);
-                    
-                    // 转换为Vec后再更新UI
-                    let mut file_infos = Vec::new();
-                    for i in 0..search_results.row_count() {
-                        if let Some(info) = search_results.row_data(i) {
-                            file_infos.push(info);
-                        }
-                    }
-                    ui.set_search_results(slint::VecModel::from_slice(&file_infos));
                 }
             }
         };
@@ -1300,35 +1790,16 @@ impl UIHandler {    /// 创建新的UI处理器
         let select_all = move |selected: bool| {
             if let Some(ui) = ui_weak.upgrade() {
                 // 更新所有文件的选择状态
-                let mut file_infos = Vec::new();
                 for i in 0..search_results.row_count() {
                     if let Some(mut info) = search_results.row_data(i) {
                         info.selected = selected;
-                        search_results.set_row_data(i, info.clone());
-                        file_infos.push(info);
+                        search_results.set_row_data(i, info);
                     }
                 }
                 
-                // 更新UI
-                ui.set_search_results(slint::VecModel::from_slice(&file_infos));
-                
-                println!("{}全选 {} 个文件", 
-                    if selected { "" } else { "取消" }, 
-                    search_results.row_count()
-                );
-                
-                // 如果是全选，打印所有文件路径
                 if selected {
-                    let mut selected_files = Vec::new();
-                    for i in 0..search_results.row_count() {
-                        if let Some(file_info) = search_results.row_data(i) {
-                            selected_files.push(file_info.path.as_str().to_string());
-                        }
-                    }
-                    println!("当前选中的文件: {:?}", selected_files);
-                    ui.set_selected_count(selected_files.len() as i32);
+                    ui.set_selected_count(search_results.row_count() as i32);
                 } else {
-                    println!("当前选中的文件: []");
                     ui.set_selected_count(0);
                 }
             }
@@ -1337,19 +1808,18 @@ impl UIHandler {    /// 创建新的UI处理器
         // 7. 排序功能回调
         let ui_weak = self.ui.as_weak();
         let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
         let sort_results_callback = move |sort_type: SharedString, reversed: bool| {
             if let Some(ui) = ui_weak.upgrade() {
+                // 搜索过程中结果还在不断加入，结束后再排序
+                if ui.get_search_running() {
+                    return;
+                }
                 // 获取搜索结果
                 let mut files = Vec::new();
                 for i in 0..search_results.row_count() {
                     if let Some(file_info) = search_results.row_data(i) {
-                        files.push(SingleFileInformations {
-                            path: PathBuf::from(file_info.path.as_str()),
-                            name: file_info.name.as_str().to_string(),
-                            size: file_info.size as u64,
-                            time: file_info.time as u64,
-                            hash: file_info.hash.as_str().to_string(),
-                        });
+                        files.push(stored_file(&result_files, &file_info));
                     }
                 }
                 
@@ -1365,37 +1835,14 @@ impl UIHandler {    /// 创建新的UI处理器
                 
                 // 获取排序类型和方向
                 let sort_type_str = sort_type.as_str();
-                println!("开始排序，排序方式: {}, 方向: {}", sort_type_str, if reversed {"从大到小"} else {"从小到大"});
                 
                 // 执行排序操作
                 SearchHelper::sort_results(&mut files, sort_type_str, reversed);
                 
-                // 清空现有的搜索结果
-                while search_results.row_count() > 0 {
-                    search_results.remove(search_results.row_count() as usize - 1);
-                }
-                
-                println!("清空之前的搜索结果，准备更新排序后的结果");
-                
-                // 创建新的搜索结果
-                let mut file_infos = Vec::new();
-                for file in &files {
-                    let file_info = FileInfo {
-                        path: file.path.to_string_lossy().to_string().into(),
-                        name: file.name.clone().into(),
-                        size: file.size as i32,
-                        time: file.time as i32,
-                        hash: file.hash.clone().into(),
-                        selected: false, // 默认不选中
-                    };
-                    search_results.push(file_info.clone());
-                    file_infos.push(file_info);
-                }
-                
-                println!("转换为Vec后，搜索结果数量: {}", file_infos.len());
-                
-                // 更新UI
-                ui.set_search_results(slint::VecModel::from_slice(&file_infos));
+                // 用排序后的结果替换现有的搜索结果，UI绑定的模型会自动刷新
+                let file_count = files.len();
+                replace_results(&search_results, &result_files, files);
+                ui.set_selected_count(0);
                 
                 MessageDialog::new()
                     .set_type(MessageType::Info)
@@ -1405,6 +1852,8 @@ impl UIHandler {    /// 创建新的UI处理器
                             "name" => "文件名",
                             "size" => "文件大小",
                             "time" => "修改日期",
+                            "created" => "创建日期",
+                            "accessed" => "访问日期",
                             "path" => "文件路径",
                             _ => "未知方式",
                         },
@@ -1413,17 +1862,85 @@ impl UIHandler {    /// 创建新的UI处理器
                         } else {
                             "从小到大"
                         },
-                        files.len()
+                        file_count
                     ))
                     .show_alert()
                     .unwrap();
             }
         };
 
+        // 7. 清理空文件/文件夹按钮回调
+        // 只处理选中的条目，移除前逐个确认仍然为空，因此搜索之后被写入内容的文件不会被删除
+        let ui_weak = self.ui.as_weak();
+        let search_results = self.search_results.inner.clone();
+        let result_files = self.search_results.files.clone();
+        let current_filter = self.current_filter.clone();
+        let clean_empty_callback = move || {
+            if let Some(ui) = ui_weak.upgrade() {
+                let files: Vec<SingleFileInformations> = search_results.iter()
+                    .filter(|file_info| file_info.selected)
+                    .map(|file_info| stored_file(&result_files, &file_info))
+                    .collect();
+                if files.is_empty() {
+                    MessageDialog::new()
+                        .set_type(MessageType::Info)
+                        .set_title("提示")
+                        .set_text("请先选择要清理的空文件和空文件夹！\n可以在过滤设置中勾选\"只查找空文件和空文件夹\"后搜索")
+                        .show_alert()
+                        .unwrap();
+                    return;
+                }
+                
+                let confirm = MessageDialog::new()
+                    .set_type(MessageType::Warning)
+                    .set_title("确认清理")
+                    .set_text(&format!("确定要把选中的 {} 个条目移到回收站吗？\n仍然为空的条目才会被移除", files.len()))
+                    .show_confirm()
+                    .unwrap_or(false);
+                if !confirm {
+                    return;
+                }
+                
+                let junk = {
+                    let filter = current_filter.borrow();
+                    if filter.junk_as_empty { filter.junk_names.clone() } else { Vec::new() }
+                };
+                let report = SearchHelper::clean_empty(&files, &junk);
+                
+                // 从搜索结果中移除已经移到回收站的条目
+                let removed: std::collections::HashSet<&PathBuf> = report.removed.iter().collect();
+                let remaining: Vec<FileInfo> = search_results.iter()
+                    .filter(|file_info| !removed.contains(&PathBuf::from(file_info.path.as_str())))
+                    .collect();
+                search_results.set_vec(remaining);
+                ui.set_selected_count(search_results.iter().filter(|file_info| file_info.selected).count() as i32);
+                
+                let mut text = format!("已移到回收站 {} 个条目", report.removed.len());
+                if !report.skipped.is_empty() {
+                    text.push_str(&format!("\n{} 个条目已经不再为空，没有移除", report.skipped.len()));
+                }
+                if !report.errors.is_empty() {
+                    text.push_str(&format!("\n{} 个条目移除失败:", report.errors.len()));
+                    for (path, error) in report.errors.iter().take(10) {
+                        text.push_str(&format!("\n{}: {}", path.display(), error));
+                    }
+                }
+                MessageDialog::new()
+                    .set_type(if report.errors.is_empty() { MessageType::Info } else { MessageType::Warning })
+                    .set_title("清理完成")
+                    .set_text(&text)
+                    .show_alert()
+                    .unwrap();
+            }
+        };
+
         // 通过全局接口暴露回调
         self.ui.on_handle_search_clicked(search_callback);
+        self.ui.on_handle_stop_search(stop_search_callback);
         self.ui.on_handle_import_results(import_callback);
         self.ui.on_handle_export_results(export_callback);
+        self.ui.on_handle_import_checksums(import_checksums_callback);
+        self.ui.on_handle_export_checksums(export_checksums_callback);
         self.ui.on_handle_show_tree_view(show_tree_view_callback);
         self.ui.on_handle_select_folder(select_folder_callback);
         self.ui.on_handle_move_selected_files(move_files_callback);
@@ -1431,18 +1948,261 @@ impl UIHandler {    /// 创建新的UI处理器
         self.ui.on_handle_delete_selected_files(delete_files_callback);
         self.ui.on_handle_map_files(map_files_callback);
         self.ui.on_handle_remove_duplicates(remove_duplicates_callback);
+        self.ui.on_handle_clean_empty(clean_empty_callback);
         self.ui.on_handle_open_folder(open_folder_callback);
         self.ui.on_handle_sort_results(sort_results_callback);
         self.ui.on_item_selected_changed(item_selected_changed);
         self.ui.on_select_all(select_all);
           // 设置初始数据绑定
-        // 先将search_results模型转换为Vec，然后再创建一个新的VecModel
-        let mut file_infos = Vec::new();
-        for i in 0..self.search_results.inner.row_count() {
-            if let Some(info) = self.search_results.inner.row_data(i) {
-                file_infos.push(info);            }
-        }
-          self.ui.set_search_results(slint::VecModel::from_slice(&file_infos));
+        // UI直接绑定search_results模型，之后对模型的修改(包括搜索线程逐批追加的结果)都会自动显示
+        self.ui.set_search_results(self.search_results.inner.clone().into());
         self.ui.set_selected_paths(self.selected_paths.clone().into());
     }
+    
+    /// 设置哈希值缓存维护的回调
+    fn setup_hash_cache_callbacks(&self) {
+        self.ui.set_hash_cache_status(hash_cache_location_text().into());
+        
+        // 清理失效条目
+        let ui_weak = self.ui.as_weak();
+        self.ui.on_handle_prune_hash_cache(move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            let status = match SearchHelper::prune_hash_cache() {
+                Ok((removed, remaining)) => format!("已清理 {} 个失效条目，剩余 {} 个\n{}", removed, remaining, hash_cache_location_text()),
+                Err(e) => format!("清理缓存失败: {}", e),
+            };
+            ui.set_hash_cache_status(status.into());
+        });
+        
+        // 清空缓存
+        let ui_weak = self.ui.as_weak();
+        self.ui.on_handle_clear_hash_cache(move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            let confirm = MessageDialog::new()
+                .set_type(MessageType::Warning)
+                .set_title("确认清空")
+                .set_text("确定要清空哈希值缓存吗？之后记录哈希值时需要重新读取所有文件")
+                .show_confirm()
+                .unwrap_or(false);
+            if !confirm {
+                return;
+            }
+            let status = match SearchHelper::clear_hash_cache() {
+                Ok(()) => format!("缓存已清空\n{}", hash_cache_location_text()),
+                Err(e) => format!("清空缓存失败: {}", e),
+            };
+            ui.set_hash_cache_status(status.into());
+        });
+    }
+    
+    /// 设置重复文件视图相关的回调
+    fn setup_duplicate_callbacks(&self) {
+        // 手动切换某个文件是否保留
+        let ui_weak = self.ui.as_weak();
+        let duplicate_groups = self.duplicate_groups.clone();
+        self.ui.on_handle_duplicate_keeper_toggled(move |group_index, file_index| {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            let mut groups = duplicate_groups.lock().unwrap();
+            if let Some(keep) = groups.get_mut(group_index as usize).and_then(|group| group.keep.get_mut(file_index as usize)) {
+                *keep = !*keep;
+            }
+            refresh_duplicate_view(&ui, &groups);
+        });
+        
+        // 按规则重新选择每组保留的文件
+        let ui_weak = self.ui.as_weak();
+        let duplicate_groups = self.duplicate_groups.clone();
+        self.ui.on_handle_apply_keeper_policy(move |policy, folder| {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            if policy == 3 && folder.trim().is_empty() {
+                MessageDialog::new()
+                    .set_type(MessageType::Info)
+                    .set_title("提示")
+                    .set_text("请先输入或选择要保留的文件所在的文件夹！")
+                    .show_alert()
+                    .unwrap();
+                return;
+            }
+            let policy = keeper_policy(policy, folder.as_str());
+            let mut groups = duplicate_groups.lock().unwrap();
+            for group in groups.iter_mut() {
+                group.apply_policy(&policy);
+            }
+            refresh_duplicate_view(&ui, &groups);
+        });
+        
+        // 选择按文件夹保留时的文件夹，选择后立即应用
+        let ui_weak = self.ui.as_weak();
+        self.ui.on_handle_choose_preferred_folder(move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            if let Ok(Some(path)) = FileDialog::new().show_open_single_dir() {
+                let folder: SharedString = path.to_string_lossy().to_string().into();
+                ui.set_preferred_folder(folder.clone());
+                ui.invoke_handle_apply_keeper_policy(3, folder);
+            }
+        });
+        
+        // 未保留的文件移到回收站，在后台线程中进行
+        let ui_weak = self.ui.as_weak();
+        let duplicate_groups = self.duplicate_groups.clone();
+        let current_cancel = self.current_cancel.clone();
+        let progress_timer = self.progress_timer.clone();
+        self.ui.on_handle_trash_duplicates(move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            if ui.get_search_running() {
+                return;
+            }
+            let count: usize = duplicate_groups.lock().unwrap().iter().filter(|group| group.has_keeper()).map(|group| group.duplicates().count()).sum();
+            if count == 0 {
+                return;
+            }
+            let confirm = MessageDialog::new()
+                .set_type(MessageType::Warning)
+                .set_title("确认删除")
+                .set_text(&format!("确定要把 {} 个未保留的重复文件移到回收站吗？", count))
+                .show_confirm()
+                .unwrap_or(false);
+            if !confirm {
+                return;
+            }
+            start_duplicate_action(&ui, &duplicate_groups, &current_cancel, &progress_timer, "移到回收站", true,
+                crate::search_file::duplicates::trash_duplicates);
+        });
+        
+        // 未保留的文件移动到目标文件夹，在后台线程中进行
+        let ui_weak = self.ui.as_weak();
+        let duplicate_groups = self.duplicate_groups.clone();
+        let current_cancel = self.current_cancel.clone();
+        let progress_timer = self.progress_timer.clone();
+        let selected_paths = self.selected_paths.clone();
+        self.ui.on_handle_move_duplicates(move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            if ui.get_search_running() {
+                return;
+            }
+            let Some(destination) = selected_paths.row_data(0) else {
+                MessageDialog::new()
+                    .set_type(MessageType::Info)
+                    .set_title("提示")
+                    .set_text("请先设置一个目标文件夹！")
+                    .show_alert()
+                    .unwrap();
+                return;
+            };
+            let destination = PathBuf::from(destination.as_str());
+            let count: usize = duplicate_groups.lock().unwrap().iter().filter(|group| group.has_keeper()).map(|group| group.duplicates().count()).sum();
+            if count == 0 {
+                return;
+            }
+            let confirm = MessageDialog::new()
+                .set_type(MessageType::Warning)
+                .set_title("确认移动")
+                .set_text(&format!("确定要把 {} 个未保留的重复文件移动到 {} 吗？", count, destination.display()))
+                .show_confirm()
+                .unwrap_or(false);
+            if !confirm {
+                return;
+            }
+            start_duplicate_action(&ui, &duplicate_groups, &current_cancel, &progress_timer, "移动", true,
+                move |groups, progress, cancel| crate::search_file::duplicates::move_duplicates(groups, &destination, progress, cancel));
+        });
+        
+        // 未保留的文件替换为指向保留文件的链接
+        // 逐字节比较和替换可能需要较长时间，在后台线程中进行
+        let ui_weak = self.ui.as_weak();
+        let duplicate_groups = self.duplicate_groups.clone();
+        let current_cancel = self.current_cancel.clone();
+        let progress_timer = self.progress_timer.clone();
+        self.ui.on_handle_link_duplicates(move || {
+            let Some(ui) = ui_weak.upgrade() else { return };
+            if ui.get_search_running() {
+                return;
+            }
+            let count: usize = duplicate_groups.lock().unwrap().iter().filter(|group| group.has_keeper()).map(|group| group.duplicates().count()).sum();
+            if count == 0 {
+                return;
+            }
+            let confirm = MessageDialog::new()
+                .set_type(MessageType::Warning)
+                .set_title("确认替换")
+                .set_text(&format!("确定要把 {} 个未保留的重复文件替换为指向保留文件的链接吗？\n替换前会逐字节比较内容；之后修改其中一个硬链接会同时改变所有副本", count))
+                .show_confirm()
+                .unwrap_or(false);
+            if !confirm {
+                return;
+            }
+            start_duplicate_action(&ui, &duplicate_groups, &current_cancel, &progress_timer, "替换为链接", false,
+                crate::search_file::duplicates::link_duplicates);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 超过2GiB的目录大小和文件数量在UI中按原值显示，不会被截断为负数
+    #[test]
+    fn large_directory_is_not_truncated() {
+        let directory = SingleFileInformations {
+            path: PathBuf::from("/data/videos"),
+            name: "videos".to_string(),
+            size: 5 * 1024 * 1024 * 1024,
+            time: 0,
+            hash: String::new(),
+            hash_algorithm: HashAlgorithm::default(),
+            link_target: None,
+            matches: Vec::new(),
+            created: None,
+            accessed: None,
+            is_dir: true,
+            file_count: 3_000_000_000,
+        };
+        let info = to_file_info(&directory, 7);
+        assert_eq!(info.size_text.as_str(), "5.0GB");
+        assert_eq!(info.file_count_text.as_str(), "3000000000");
+        assert_eq!(info.created_text.as_str(), "");
+
+        // 操作使用的是Rust一侧保存的完整结果
+        let files = Mutex::new(Vec::new());
+        let rows = store_files(&files, vec![directory]);
+        assert_eq!(stored_file(&files, &rows[0]).size, 5 * 1024 * 1024 * 1024);
+    }
+
+    // 2038年之后(超过i32::MAX秒)的时间按原值显示，不会回绕为1901年
+    #[test]
+    fn time_after_2038_is_not_wrapped() {
+        let seconds = i32::MAX as u64 + 24 * 60 * 60;
+        assert!(format_time(seconds).starts_with("2038-01-"));
+        assert_eq!(format_time(4_102_444_800 + 12 * 60 * 60)[..10], *"2100-01-01");
+        assert_eq!(format_time(u64::MAX), "");
+    }
+
+    // 查找重复文件时从UI行取回完整结果，超过i32::MAX的文件大小不会被截断
+    #[test]
+    fn large_duplicates_keep_their_size() {
+        let size = i32::MAX as u64 + 1;
+        let file = |path: &str| SingleFileInformations {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap().to_string(),
+            size,
+            time: 0,
+            hash: "0123456789abcdef".to_string(),
+            hash_algorithm: HashAlgorithm::default(),
+            link_target: None,
+            matches: Vec::new(),
+            created: None,
+            accessed: None,
+            is_dir: false,
+            file_count: 0,
+        };
+        let files = Mutex::new(Vec::new());
+        let rows = store_files(&files, vec![file("/data/a.iso"), file("/data/b.iso")]);
+        assert_eq!(rows[0].size_text.as_str(), "2.0GB");
+
+        let restored: Vec<SingleFileInformations> = rows.iter().map(|row| stored_file(&files, row)).collect();
+        let groups = crate::search_file::duplicates::into_groups(&restored, &[vec![0, 1]]);
+        assert_eq!(groups[0].size, size);
+        assert_eq!(groups[0].reclaimable(), size);
+    }
 }